@@ -2,9 +2,27 @@
 
 pub mod prelude;
 
+pub mod account_action;
+pub mod audit_event;
+pub mod data_export;
+pub mod email_verification;
 pub mod follower;
+pub mod jwt_key;
+pub mod login_challenge;
+pub mod login_failure;
+pub mod media;
+pub mod password_reset;
+pub mod permission;
+pub mod personal_access_token;
 pub mod post;
 pub mod post_like;
+pub mod post_moderation;
+pub mod post_revision;
+pub mod recovery_code;
+pub mod refresh_token;
 pub mod role;
+pub mod role_permission;
 pub mod session;
+pub mod throttle_bucket;
 pub mod user;
+pub mod user_totp;
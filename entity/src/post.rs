@@ -14,10 +14,20 @@ pub struct Model {
     pub likes: i32,
     pub comments: i32,
     pub created_at: DateTime,
+    pub kind: String,
+    pub reposts: i32,
+    pub quotes: i32,
+    pub edited_at: Option<DateTime>,
+    pub edit_count: i32,
+    pub deleted_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::media::Entity")]
+    Media,
+    #[sea_orm(has_many = "super::post_revision::Entity")]
+    PostRevision,
     #[sea_orm(
         belongs_to = "Entity",
         from = "Column::RelatedToPost",
@@ -36,6 +46,18 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::media::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Media.def()
+    }
+}
+
+impl Related<super::post_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostRevision.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
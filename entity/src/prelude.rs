@@ -1,8 +1,26 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.11.0
 
+pub use super::account_action::Entity as AccountAction;
+pub use super::audit_event::Entity as AuditEvent;
+pub use super::data_export::Entity as DataExport;
+pub use super::email_verification::Entity as EmailVerification;
 pub use super::follower::Entity as Follower;
+pub use super::jwt_key::Entity as JwtKey;
+pub use super::login_challenge::Entity as LoginChallenge;
+pub use super::login_failure::Entity as LoginFailure;
+pub use super::media::Entity as Media;
+pub use super::password_reset::Entity as PasswordReset;
+pub use super::permission::Entity as Permission;
+pub use super::personal_access_token::Entity as PersonalAccessToken;
 pub use super::post::Entity as Post;
 pub use super::post_like::Entity as PostLike;
+pub use super::post_moderation::Entity as PostModeration;
+pub use super::post_revision::Entity as PostRevision;
+pub use super::recovery_code::Entity as RecoveryCode;
+pub use super::refresh_token::Entity as RefreshToken;
 pub use super::role::Entity as Role;
+pub use super::role_permission::Entity as RolePermission;
 pub use super::session::Entity as Session;
+pub use super::throttle_bucket::Entity as ThrottleBucket;
 pub use super::user::Entity as User;
+pub use super::user_totp::Entity as UserTotp;
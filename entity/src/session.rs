@@ -5,14 +5,21 @@ use sea_orm::entity::prelude::*;
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "session")]
 pub struct Model {
-    #[sea_orm(primary_key, auto_increment = false)]
-    pub jwt: String,
+    #[sea_orm(primary_key)]
+    pub id: i32,
     pub user_id: i32,
     pub created_at: DateTime,
+    pub revoked_at: Option<DateTime>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub last_used_at: DateTime,
+    pub expires_at: DateTime,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::refresh_token::Entity")]
+    RefreshToken,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::UserId",
@@ -23,6 +30,12 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::refresh_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RefreshToken.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -10,19 +10,48 @@ pub struct Model {
     pub id: i32,
     pub username: String,
     pub email: String,
+    #[serde(skip_serializing)]
     pub password: String,
     pub followers: i32,
     pub following: i32,
     pub role: i16,
     pub created_at: DateTime,
+    pub email_verified_at: Option<DateTime>,
+    pub suspended_until: Option<DateTime>,
+    pub banned_at: Option<DateTime>,
+    pub deleted_at: Option<DateTime>,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub location: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    pub banner_url: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::account_action::Entity")]
+    AccountAction,
+    #[sea_orm(has_many = "super::data_export::Entity")]
+    DataExport,
+    #[sea_orm(has_many = "super::email_verification::Entity")]
+    EmailVerification,
+    #[sea_orm(has_many = "super::login_challenge::Entity")]
+    LoginChallenge,
+    #[sea_orm(has_many = "super::media::Entity")]
+    Media,
+    #[sea_orm(has_many = "super::password_reset::Entity")]
+    PasswordReset,
+    #[sea_orm(has_many = "super::personal_access_token::Entity")]
+    PersonalAccessToken,
     #[sea_orm(has_many = "super::post::Entity")]
     Post,
     #[sea_orm(has_many = "super::post_like::Entity")]
     PostLike,
+    #[sea_orm(has_many = "super::post_moderation::Entity")]
+    PostModeration,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
     #[sea_orm(
         belongs_to = "super::role::Entity",
         from = "Column::Role",
@@ -33,6 +62,50 @@ pub enum Relation {
     Role,
     #[sea_orm(has_many = "super::session::Entity")]
     Session,
+    #[sea_orm(has_one = "super::user_totp::Entity")]
+    UserTotp,
+}
+
+impl Related<super::account_action::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AccountAction.def()
+    }
+}
+
+impl Related<super::data_export::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DataExport.def()
+    }
+}
+
+impl Related<super::email_verification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EmailVerification.def()
+    }
+}
+
+impl Related<super::login_challenge::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LoginChallenge.def()
+    }
+}
+
+impl Related<super::media::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Media.def()
+    }
+}
+
+impl Related<super::password_reset::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PasswordReset.def()
+    }
+}
+
+impl Related<super::personal_access_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PersonalAccessToken.def()
+    }
 }
 
 impl Related<super::post::Entity> for Entity {
@@ -47,6 +120,18 @@ impl Related<super::post_like::Entity> for Entity {
     }
 }
 
+impl Related<super::post_moderation::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostModeration.def()
+    }
+}
+
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
 impl Related<super::role::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Role.def()
@@ -59,4 +144,10 @@ impl Related<super::session::Entity> for Entity {
     }
 }
 
+impl Related<super::user_totp::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserTotp.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,12 +1,64 @@
 pub use sea_orm_migration::prelude::*;
 
+// the baseline migration predates these lints
+#[allow(
+    clippy::enum_variant_names,
+    clippy::unnecessary_cast,
+    clippy::upper_case_acronyms
+)]
 mod m20220101_000001_create_table;
+mod m20261018_000002_create_jwt_key_table;
+mod m20261018_000003_add_algorithm_to_jwt_key;
+mod m20261018_000004_create_refresh_token_table;
+mod m20261018_000005_add_client_info_to_session;
+mod m20261018_000006_create_email_verification_table;
+mod m20261018_000007_create_password_reset_table;
+mod m20261018_000008_create_two_factor_tables;
+mod m20261018_000009_create_throttle_tables;
+mod m20261018_000010_create_personal_access_token_table;
+mod m20261018_000011_create_permission_tables;
+mod m20261018_000012_create_post_moderation_table;
+mod m20261018_000013_add_sanctions_to_user;
+mod m20261018_000014_add_deleted_at_to_user;
+mod m20261018_000015_add_profile_to_user;
+mod m20261018_000016_create_data_export_table;
+mod m20261018_000017_create_audit_event_table;
+mod m20261018_000018_normalize_user_identifiers;
+mod m20261018_000019_add_reply_index_to_post;
+mod m20261018_000020_add_kind_to_post;
+mod m20261018_000021_create_post_revision_table;
+mod m20261018_000022_add_deleted_at_to_post;
+mod m20261018_000023_create_media_table;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261018_000002_create_jwt_key_table::Migration),
+            Box::new(m20261018_000003_add_algorithm_to_jwt_key::Migration),
+            Box::new(m20261018_000004_create_refresh_token_table::Migration),
+            Box::new(m20261018_000005_add_client_info_to_session::Migration),
+            Box::new(m20261018_000006_create_email_verification_table::Migration),
+            Box::new(m20261018_000007_create_password_reset_table::Migration),
+            Box::new(m20261018_000008_create_two_factor_tables::Migration),
+            Box::new(m20261018_000009_create_throttle_tables::Migration),
+            Box::new(m20261018_000010_create_personal_access_token_table::Migration),
+            Box::new(m20261018_000011_create_permission_tables::Migration),
+            Box::new(m20261018_000012_create_post_moderation_table::Migration),
+            Box::new(m20261018_000013_add_sanctions_to_user::Migration),
+            Box::new(m20261018_000014_add_deleted_at_to_user::Migration),
+            Box::new(m20261018_000015_add_profile_to_user::Migration),
+            Box::new(m20261018_000016_create_data_export_table::Migration),
+            Box::new(m20261018_000017_create_audit_event_table::Migration),
+            Box::new(m20261018_000018_normalize_user_identifiers::Migration),
+            Box::new(m20261018_000019_add_reply_index_to_post::Migration),
+            Box::new(m20261018_000020_add_kind_to_post::Migration),
+            Box::new(m20261018_000021_create_post_revision_table::Migration),
+            Box::new(m20261018_000022_add_deleted_at_to_post::Migration),
+            Box::new(m20261018_000023_create_media_table::Migration),
+        ]
     }
 }
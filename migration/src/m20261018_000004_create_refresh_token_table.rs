@@ -0,0 +1,158 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Replaces JWT keyed sessions with session families of rotating refresh tokens
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // year long JWTs can not be converted, their owners have to log in again
+        manager
+            .drop_table(Table::drop().if_exists().table(Session::Table).to_owned())
+            .await?;
+
+        /* SESSION */
+        manager
+            .create_table(
+                Table::create()
+                    .table(Session::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Session::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Session::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(Session::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .col(ColumnDef::new(Session::RevokedAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__session__to__user")
+                            .from_col(Session::UserId)
+                            .to_col(User::Id)
+                            .from_tbl(Session::Table)
+                            .to_tbl(User::Table),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        /* REFRESH_TOKEN */
+        manager
+            .create_table(
+                Table::create()
+                    .table(RefreshToken::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RefreshToken::TokenHash)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RefreshToken::SessionId).integer().not_null())
+                    .col(
+                        ColumnDef::new(RefreshToken::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(RefreshToken::ExpiresAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RefreshToken::UsedAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__refresh_token__to__session")
+                            .from_col(RefreshToken::SessionId)
+                            .to_col(Session::Id)
+                            .from_tbl(RefreshToken::Table)
+                            .to_tbl(Session::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(RefreshToken::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().if_exists().table(Session::Table).to_owned())
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Session::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Session::Jwt)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Session::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(Session::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__session__to__user")
+                            .from_col(Session::UserId)
+                            .to_col(User::Id)
+                            .from_tbl(Session::Table)
+                            .to_tbl(User::Table),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Session {
+    Table,
+    Id,
+    Jwt,
+    UserId,
+    CreatedAt,
+    RevokedAt,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Iden)]
+enum RefreshToken {
+    Table,
+    TokenHash,
+    SessionId,
+    CreatedAt,
+    ExpiresAt,
+    UsedAt,
+}
@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Session::Table)
+                    .add_column(ColumnDef::new(Session::UserAgent).string().null())
+                    .add_column(ColumnDef::new(Session::Ip).string().null())
+                    .add_column(
+                        ColumnDef::new(Session::LastUsedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .add_column(ColumnDef::new(Session::ExpiresAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // existing sessions live as long as their newest refresh token,
+        // new ones get their expiry from the application
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"UPDATE session s SET expires_at = COALESCE(
+                (SELECT max(r.expires_at) FROM refresh_token r WHERE r.session_id = s.id),
+                CURRENT_TIMESTAMP
+            )"#,
+        )
+        .await?;
+        db.execute_unprepared(r#"ALTER TABLE session ALTER COLUMN expires_at SET NOT NULL"#)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Session::Table)
+                    .drop_column(Session::UserAgent)
+                    .drop_column(Session::Ip)
+                    .drop_column(Session::LastUsedAt)
+                    .drop_column(Session::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Session {
+    Table,
+    UserAgent,
+    Ip,
+    LastUsedAt,
+    ExpiresAt,
+}
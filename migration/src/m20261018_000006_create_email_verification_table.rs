@@ -0,0 +1,100 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::EmailVerifiedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // accounts created before the verification flow stay usable
+        let db = manager.get_connection();
+        db.execute_unprepared(r#"UPDATE "user" SET email_verified_at = created_at"#)
+            .await?;
+
+        /* EMAIL_VERIFICATION */
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailVerification::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EmailVerification::TokenHash)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(EmailVerification::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EmailVerification::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(EmailVerification::ExpiresAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__email_verification__to__user")
+                            .from_col(EmailVerification::UserId)
+                            .to_col(User::Id)
+                            .from_tbl(EmailVerification::Table)
+                            .to_tbl(User::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(EmailVerification::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::EmailVerifiedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+    EmailVerifiedAt,
+}
+
+#[derive(Iden)]
+enum EmailVerification {
+    Table,
+    TokenHash,
+    UserId,
+    CreatedAt,
+    ExpiresAt,
+}
@@ -0,0 +1,178 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        /* USER_TOTP */
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserTotp::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserTotp::UserId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UserTotp::Secret).string().not_null())
+                    .col(
+                        ColumnDef::new(UserTotp::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .col(ColumnDef::new(UserTotp::ConfirmedAt).timestamp().null())
+                    .col(ColumnDef::new(UserTotp::LastUsedStep).big_integer().null())
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__user_totp__to__user")
+                            .from_col(UserTotp::UserId)
+                            .to_col(User::Id)
+                            .from_tbl(UserTotp::Table)
+                            .to_tbl(User::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        /* RECOVERY_CODE */
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecoveryCode::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RecoveryCode::CodeHash)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RecoveryCode::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(RecoveryCode::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .col(ColumnDef::new(RecoveryCode::UsedAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__recovery_code__to__user")
+                            .from_col(RecoveryCode::UserId)
+                            .to_col(User::Id)
+                            .from_tbl(RecoveryCode::Table)
+                            .to_tbl(User::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        /* LOGIN_CHALLENGE */
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginChallenge::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LoginChallenge::TokenHash)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LoginChallenge::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(LoginChallenge::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(LoginChallenge::ExpiresAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LoginChallenge::Attempts)
+                            .small_integer()
+                            .not_null()
+                            .default(0_i16),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__login_challenge__to__user")
+                            .from_col(LoginChallenge::UserId)
+                            .to_col(User::Id)
+                            .from_tbl(LoginChallenge::Table)
+                            .to_tbl(User::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(LoginChallenge::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(RecoveryCode::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().if_exists().table(UserTotp::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum UserTotp {
+    Table,
+    UserId,
+    Secret,
+    CreatedAt,
+    ConfirmedAt,
+    LastUsedStep,
+}
+
+#[derive(Iden)]
+enum RecoveryCode {
+    Table,
+    CodeHash,
+    UserId,
+    CreatedAt,
+    UsedAt,
+}
+
+#[derive(Iden)]
+enum LoginChallenge {
+    Table,
+    TokenHash,
+    UserId,
+    CreatedAt,
+    ExpiresAt,
+    Attempts,
+}
@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        /* THROTTLE_BUCKET */
+        manager
+            .create_table(
+                Table::create()
+                    .table(ThrottleBucket::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ThrottleBucket::Key)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ThrottleBucket::FullAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        /* LOGIN_FAILURE */
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginFailure::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LoginFailure::Account)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LoginFailure::Failures).integer().not_null())
+                    .col(
+                        ColumnDef::new(LoginFailure::LastFailedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(LoginFailure::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(ThrottleBucket::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ThrottleBucket {
+    Table,
+    Key,
+    FullAt,
+}
+
+#[derive(Iden)]
+enum LoginFailure {
+    Table,
+    Account,
+    Failures,
+    LastFailedAt,
+}
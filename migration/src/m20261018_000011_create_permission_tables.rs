@@ -0,0 +1,220 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Permissions known to the server and what they allow
+const PERMISSIONS: [(&str, &str); 9] = [
+    ("users:read", "View users"),
+    ("posts:read", "View posts"),
+    ("posts:write", "Create, edit, delete and like own posts"),
+    ("feed:read", "View the feed"),
+    ("follows:read", "View followers and followings"),
+    ("follows:write", "Follow and unfollow users"),
+    ("account:manage", "Manage own sessions, tokens and 2FA"),
+    ("keys:rotate", "Rotate JWT signing keys"),
+    ("roles:manage", "Create roles and change their permissions"),
+];
+
+/// Permissions of the built-in roles, admins get every permission
+const ROLE_PERMISSIONS: [(&str, &[&str]); 2] = [
+    (
+        "moderator",
+        &[
+            "users:read",
+            "posts:read",
+            "posts:write",
+            "feed:read",
+            "follows:read",
+            "follows:write",
+            "account:manage",
+        ],
+    ),
+    (
+        "user",
+        &[
+            "users:read",
+            "posts:read",
+            "posts:write",
+            "feed:read",
+            "follows:read",
+            "follows:write",
+            "account:manage",
+        ],
+    ),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Role::Table)
+                    .add_column(
+                        ColumnDef::new(Role::PermissionsVersion)
+                            .integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx__role__name")
+                    .table(Role::Table)
+                    .col(Role::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        /* PERMISSION */
+        manager
+            .create_table(
+                Table::create()
+                    .table(Permission::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Permission::Name)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Permission::Description).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        /* ROLE_PERMISSION */
+        manager
+            .create_table(
+                Table::create()
+                    .table(RolePermission::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RolePermission::RoleId).integer().not_null())
+                    .col(
+                        ColumnDef::new(RolePermission::Permission)
+                            .string()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(RolePermission::RoleId)
+                            .col(RolePermission::Permission),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__role_permission__to__role")
+                            .from_col(RolePermission::RoleId)
+                            .to_col(Role::Id)
+                            .from_tbl(RolePermission::Table)
+                            .to_tbl(Role::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__role_permission__to__permission")
+                            .from_col(RolePermission::Permission)
+                            .to_col(Permission::Name)
+                            .from_tbl(RolePermission::Table)
+                            .to_tbl(Permission::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Filling the tables
+        let mut permissions = Query::insert();
+        permissions
+            .into_table(Permission::Table)
+            .columns([Permission::Name, Permission::Description]);
+        for (name, description) in PERMISSIONS {
+            permissions.values_panic([name.into(), description.into()]);
+        }
+        manager.exec_stmt(permissions).await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"INSERT INTO role_permission (role_id, permission)
+            SELECT role.id, permission.name FROM role, permission WHERE role.name = 'admin'"#,
+        )
+        .await?;
+
+        for (role, permissions) in ROLE_PERMISSIONS {
+            for permission in permissions {
+                db.execute_unprepared(&format!(
+                    r#"INSERT INTO role_permission (role_id, permission)
+                    SELECT id, '{permission}' FROM role WHERE name = '{role}'"#
+                ))
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(RolePermission::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(Permission::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx__role__name")
+                    .table(Role::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Role::Table)
+                    .drop_column(Role::PermissionsVersion)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Role {
+    Table,
+    Id,
+    Name,
+    PermissionsVersion,
+}
+
+#[derive(Iden)]
+enum Permission {
+    Table,
+    Name,
+    Description,
+}
+
+#[derive(Iden)]
+enum RolePermission {
+    Table,
+    RoleId,
+    Permission,
+}
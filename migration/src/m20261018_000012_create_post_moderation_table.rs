@@ -0,0 +1,137 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        /* POST_MODERATION */
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostModeration::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PostModeration::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // no foreign key, the record outlives deleted posts
+                    .col(ColumnDef::new(PostModeration::PostId).integer().not_null())
+                    .col(
+                        ColumnDef::new(PostModeration::AuthorId)
+                            .integer()
+                            .not_null(),
+                    )
+                    // cleared when the moderator deletes their account, the record stays
+                    .col(ColumnDef::new(PostModeration::ModeratorId).integer().null())
+                    .col(ColumnDef::new(PostModeration::Action).string().not_null())
+                    .col(
+                        ColumnDef::new(PostModeration::PreviousText)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PostModeration::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__post_moderation__to__user")
+                            .from_col(PostModeration::ModeratorId)
+                            .to_col(User::Id)
+                            .from_tbl(PostModeration::Table)
+                            .to_tbl(User::Table)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Moderators and admins may change posts of other users
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Permission::Table)
+                    .columns([Permission::Name, Permission::Description])
+                    .values_panic([
+                        "posts:moderate".into(),
+                        "Edit and delete posts of other users".into(),
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"INSERT INTO role_permission (role_id, permission)
+            SELECT id, 'posts:moderate' FROM role WHERE name IN ('admin', 'moderator')"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"UPDATE role SET permissions_version = permissions_version + 1
+            WHERE name IN ('admin', 'moderator')"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"UPDATE role SET permissions_version = permissions_version + 1
+            WHERE id IN (SELECT role_id FROM role_permission WHERE permission = 'posts:moderate')"#,
+        )
+        .await?;
+
+        // role_permission rows cascade
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(Permission::Table)
+                    .and_where(Expr::col(Permission::Name).eq("posts:moderate"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(PostModeration::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum PostModeration {
+    Table,
+    Id,
+    PostId,
+    AuthorId,
+    ModeratorId,
+    Action,
+    PreviousText,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Permission {
+    Table,
+    Name,
+    Description,
+}
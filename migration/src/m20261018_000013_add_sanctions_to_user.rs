@@ -0,0 +1,151 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::SuspendedUntil).timestamp().null())
+                    .add_column(ColumnDef::new(User::BannedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        /* ACCOUNT_ACTION */
+        manager
+            .create_table(
+                Table::create()
+                    .table(AccountAction::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AccountAction::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AccountAction::UserId).integer().not_null())
+                    .col(ColumnDef::new(AccountAction::AdminId).integer().not_null())
+                    .col(ColumnDef::new(AccountAction::Action).string().not_null())
+                    .col(ColumnDef::new(AccountAction::Reason).string().not_null())
+                    .col(ColumnDef::new(AccountAction::Role).small_integer().null())
+                    .col(ColumnDef::new(AccountAction::Until).timestamp().null())
+                    .col(
+                        ColumnDef::new(AccountAction::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__account_action__to__user")
+                            .from_col(AccountAction::UserId)
+                            .to_col(User::Id)
+                            .from_tbl(AccountAction::Table)
+                            .to_tbl(User::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only admins may change roles and sanction accounts
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Permission::Table)
+                    .columns([Permission::Name, Permission::Description])
+                    .values_panic([
+                        "users:manage".into(),
+                        "Change roles of users, suspend and ban them".into(),
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"INSERT INTO role_permission (role_id, permission)
+            SELECT id, 'users:manage' FROM role WHERE name = 'admin'"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"UPDATE role SET permissions_version = permissions_version + 1
+            WHERE name = 'admin'"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"UPDATE role SET permissions_version = permissions_version + 1
+            WHERE id IN (SELECT role_id FROM role_permission WHERE permission = 'users:manage')"#,
+        )
+        .await?;
+
+        // role_permission rows cascade
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(Permission::Table)
+                    .and_where(Expr::col(Permission::Name).eq("users:manage"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(AccountAction::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::SuspendedUntil)
+                    .drop_column(User::BannedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+    SuspendedUntil,
+    BannedAt,
+}
+
+#[derive(Iden)]
+enum AccountAction {
+    Table,
+    Id,
+    UserId,
+    AdminId,
+    Action,
+    Reason,
+    Role,
+    Until,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Permission {
+    Table,
+    Name,
+    Description,
+}
@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::DisplayName).string().null())
+                    .add_column(ColumnDef::new(User::Bio).string().null())
+                    .add_column(ColumnDef::new(User::Location).string().null())
+                    .add_column(ColumnDef::new(User::Website).string().null())
+                    .add_column(ColumnDef::new(User::AvatarUrl).string().null())
+                    .add_column(ColumnDef::new(User::BannerUrl).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // new address of an email change, confirmed by the token
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EmailVerification::Table)
+                    .add_column(ColumnDef::new(EmailVerification::Email).string().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EmailVerification::Table)
+                    .drop_column(EmailVerification::Email)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::DisplayName)
+                    .drop_column(User::Bio)
+                    .drop_column(User::Location)
+                    .drop_column(User::Website)
+                    .drop_column(User::AvatarUrl)
+                    .drop_column(User::BannerUrl)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    DisplayName,
+    Bio,
+    Location,
+    Website,
+    AvatarUrl,
+    BannerUrl,
+}
+
+#[derive(Iden)]
+enum EmailVerification {
+    Table,
+    Email,
+}
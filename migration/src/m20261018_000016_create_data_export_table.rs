@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        /* DATA_EXPORT */
+        manager
+            .create_table(
+                Table::create()
+                    .table(DataExport::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DataExport::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(DataExport::UserId).integer().not_null())
+                    .col(ColumnDef::new(DataExport::Status).string().not_null())
+                    .col(ColumnDef::new(DataExport::FilePath).string().null())
+                    .col(
+                        ColumnDef::new(DataExport::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .col(ColumnDef::new(DataExport::FinishedAt).timestamp().null())
+                    .col(ColumnDef::new(DataExport::ExpiresAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__data_export__to__user")
+                            .from_col(DataExport::UserId)
+                            .to_col(User::Id)
+                            .from_tbl(DataExport::Table)
+                            .to_tbl(User::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(DataExport::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum DataExport {
+    Table,
+    Id,
+    UserId,
+    Status,
+    FilePath,
+    CreatedAt,
+    FinishedAt,
+    ExpiresAt,
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+}
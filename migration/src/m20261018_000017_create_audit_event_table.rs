@@ -0,0 +1,150 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        /* AUDIT_EVENT */
+        // no foreign keys, events outlive the users they mention
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditEvent::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditEvent::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AuditEvent::Event).string().not_null())
+                    .col(ColumnDef::new(AuditEvent::Outcome).string().not_null())
+                    .col(ColumnDef::new(AuditEvent::ActorId).integer().null())
+                    .col(ColumnDef::new(AuditEvent::TargetId).integer().null())
+                    .col(ColumnDef::new(AuditEvent::Ip).string().null())
+                    .col(ColumnDef::new(AuditEvent::UserAgent).string().null())
+                    .col(ColumnDef::new(AuditEvent::Detail).string().null())
+                    .col(
+                        ColumnDef::new(AuditEvent::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        for (name, column) in [
+            ("idx__audit_event__actor_id", AuditEvent::ActorId),
+            ("idx__audit_event__target_id", AuditEvent::TargetId),
+            ("idx__audit_event__event", AuditEvent::Event),
+            ("idx__audit_event__created_at", AuditEvent::CreatedAt),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(AuditEvent::Table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // the table is append-only, even for the server itself
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"CREATE OR REPLACE FUNCTION audit_event_append_only() RETURNS trigger AS $$
+            BEGIN
+                RAISE EXCEPTION 'audit_event is append-only';
+            END;
+            $$ LANGUAGE plpgsql"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"CREATE TRIGGER audit_event_append_only
+            BEFORE UPDATE OR DELETE ON audit_event
+            FOR EACH ROW EXECUTE FUNCTION audit_event_append_only()"#,
+        )
+        .await?;
+
+        // Only admins read the audit log
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Permission::Table)
+                    .columns([Permission::Name, Permission::Description])
+                    .values_panic(["audit:read".into(), "Read the security audit log".into()])
+                    .to_owned(),
+            )
+            .await?;
+        db.execute_unprepared(
+            r#"INSERT INTO role_permission (role_id, permission)
+            SELECT id, 'audit:read' FROM role WHERE name = 'admin'"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"UPDATE role SET permissions_version = permissions_version + 1
+            WHERE name = 'admin'"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"UPDATE role SET permissions_version = permissions_version + 1
+            WHERE id IN (SELECT role_id FROM role_permission WHERE permission = 'audit:read')"#,
+        )
+        .await?;
+
+        // role_permission rows cascade
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(Permission::Table)
+                    .and_where(Expr::col(Permission::Name).eq("audit:read"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(AuditEvent::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        db.execute_unprepared("DROP FUNCTION IF EXISTS audit_event_append_only()")
+            .await
+            .map(|_| ())
+    }
+}
+
+#[derive(Iden, Clone)]
+enum AuditEvent {
+    Table,
+    Id,
+    Event,
+    Outcome,
+    ActorId,
+    TargetId,
+    Ip,
+    UserAgent,
+    Detail,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Permission {
+    Table,
+    Name,
+    Description,
+}
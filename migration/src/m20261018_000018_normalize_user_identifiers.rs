@@ -0,0 +1,107 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Accounts only differing in case from an older one keep working,
+        // the username gets the id as suffix and the address is replaced,
+        // so the owner logs in by username and sets a new one.
+        // Suffixes taken by another account get a counter appended until one is free
+        db.execute_unprepared(
+            r#"DO $$
+            DECLARE
+                dup RECORD;
+                candidate TEXT;
+                attempt INTEGER;
+            BEGIN
+                FOR dup IN
+                    SELECT u.id, lower(trim(u.username)) AS base FROM "user" u
+                    WHERE EXISTS (
+                        SELECT 1 FROM "user" o
+                        WHERE lower(trim(o.username)) = lower(trim(u.username)) AND o.id < u.id
+                    )
+                    ORDER BY u.id
+                LOOP
+                    candidate := dup.base || '_' || dup.id;
+                    attempt := 1;
+                    WHILE EXISTS (
+                        SELECT 1 FROM "user" o
+                        WHERE lower(trim(o.username)) = candidate AND o.id <> dup.id
+                    ) LOOP
+                        candidate := dup.base || '_' || dup.id || '_' || attempt;
+                        attempt := attempt + 1;
+                    END LOOP;
+                    UPDATE "user" SET username = candidate WHERE id = dup.id;
+                END LOOP;
+            END $$"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"UPDATE "user" u SET email = 'duplicate-' || u.id || '@duplicate.invalid'
+            WHERE EXISTS (
+                SELECT 1 FROM "user" o
+                WHERE lower(trim(o.email)) = lower(trim(u.email)) AND o.id < u.id
+            )"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"UPDATE "user" SET username = lower(trim(username)), email = lower(trim(email))"#,
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx__user__username")
+                    .table(User::Table)
+                    .col(User::Username)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx__user__email")
+                    .table(User::Table)
+                    .col(User::Email)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // the normalized values stay
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx__user__email")
+                    .table(User::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx__user__username")
+                    .table(User::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Username,
+    Email,
+}
@@ -0,0 +1,47 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx__post__related_to_post")
+                    .table(Post::Table)
+                    .col(Post::RelatedToPost)
+                    .to_owned(),
+            )
+            .await?;
+
+        // the counter was never maintained, so it starts from the actual replies
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"UPDATE post p SET comments = (
+                    SELECT count(*) FROM post r WHERE r.related_to_post = p.id
+                )"#,
+            )
+            .await
+            .map(|_| ())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx__post__related_to_post")
+                    .table(Post::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Post {
+    Table,
+    RelatedToPost,
+}
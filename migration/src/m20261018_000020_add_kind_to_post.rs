@@ -0,0 +1,78 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .add_column(
+                        ColumnDef::new(Post::Kind)
+                            .string()
+                            .not_null()
+                            .default("original"),
+                    )
+                    .add_column(
+                        ColumnDef::new(Post::Reposts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(ColumnDef::new(Post::Quotes).integer().not_null().default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+
+        // every existing relation is a reply
+        db.execute_unprepared("UPDATE post SET kind = 'reply' WHERE related_to_post IS NOT NULL")
+            .await?;
+        db.execute_unprepared(
+            r#"ALTER TABLE post ADD CONSTRAINT chk__post__kind CHECK (
+                (kind = 'original' AND related_to_post IS NULL)
+                OR kind IN ('reply', 'repost', 'quote')
+            )"#,
+        )
+        .await?;
+
+        // one repost per user and post
+        db.execute_unprepared(
+            r#"CREATE UNIQUE INDEX idx__post__repost ON post (user_id, related_to_post)
+            WHERE kind = 'repost'"#,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared("DROP INDEX IF EXISTS idx__post__repost")
+            .await?;
+        db.execute_unprepared("ALTER TABLE post DROP CONSTRAINT IF EXISTS chk__post__kind")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .drop_column(Post::Quotes)
+                    .drop_column(Post::Reposts)
+                    .drop_column(Post::Kind)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Post {
+    Table,
+    Kind,
+    Reposts,
+    Quotes,
+}
@@ -0,0 +1,110 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .add_column(ColumnDef::new(Post::EditedAt).timestamp().null())
+                    .add_column(
+                        ColumnDef::new(Post::EditCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        /* POST_REVISION */
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostRevision::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PostRevision::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PostRevision::PostId).integer().not_null())
+                    // text the post had before the edit
+                    .col(ColumnDef::new(PostRevision::Text).string().not_null())
+                    // no foreign key, moderators may delete their account
+                    .col(ColumnDef::new(PostRevision::EditorId).integer().not_null())
+                    .col(
+                        ColumnDef::new(PostRevision::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__post_revision__to__post")
+                            .from_col(PostRevision::PostId)
+                            .to_col(Post::Id)
+                            .from_tbl(PostRevision::Table)
+                            .to_tbl(Post::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx__post_revision__post_id")
+                    .table(PostRevision::Table)
+                    .col(PostRevision::PostId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .if_exists()
+                    .table(PostRevision::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .drop_column(Post::EditCount)
+                    .drop_column(Post::EditedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum PostRevision {
+    Table,
+    Id,
+    PostId,
+    Text,
+    EditorId,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Post {
+    Table,
+    Id,
+    EditedAt,
+    EditCount,
+}
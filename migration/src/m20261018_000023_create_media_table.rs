@@ -0,0 +1,113 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        /* MEDIA */
+        manager
+            .create_table(
+                Table::create()
+                    .table(Media::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Media::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Media::UserId).integer().not_null())
+                    // set once the upload is attached to a post
+                    .col(ColumnDef::new(Media::PostId).integer().null())
+                    .col(ColumnDef::new(Media::OriginalKey).string().not_null())
+                    .col(ColumnDef::new(Media::ThumbnailKey).string().not_null())
+                    .col(ColumnDef::new(Media::ContentType).string().not_null())
+                    .col(ColumnDef::new(Media::Width).integer().not_null())
+                    .col(ColumnDef::new(Media::Height).integer().not_null())
+                    .col(ColumnDef::new(Media::Size).integer().not_null())
+                    .col(ColumnDef::new(Media::AltText).string().null())
+                    .col(
+                        ColumnDef::new(Media::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT CURRENT_TIMESTAMP".to_string()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__media__to__user")
+                            .from_col(Media::UserId)
+                            .to_col(User::Id)
+                            .from_tbl(Media::Table)
+                            .to_tbl(User::Table),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk__media__to__post")
+                            .from_col(Media::PostId)
+                            .to_col(Post::Id)
+                            .from_tbl(Media::Table)
+                            .to_tbl(Post::Table)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx__media__post_id")
+                    .table(Media::Table)
+                    .col(Media::PostId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx__media__user_id")
+                    .table(Media::Table)
+                    .col(Media::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().if_exists().table(Media::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Media {
+    Table,
+    Id,
+    UserId,
+    PostId,
+    OriginalKey,
+    ThumbnailKey,
+    ContentType,
+    Width,
+    Height,
+    Size,
+    AltText,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(Iden)]
+enum Post {
+    Table,
+    Id,
+}
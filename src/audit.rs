@@ -0,0 +1,142 @@
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ConnectionTrait, Set};
+
+use entity::audit_event;
+
+use crate::models::session::ClientInfo;
+
+/// Security relevant action stored in the audit log
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Login,
+    Logout,
+    RefreshTokenReused,
+    SessionRevoked,
+    PasswordReset,
+    PasswordChanged,
+    EmailChangeRequested,
+    AccountDeleted,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    RecoveryCodesRegenerated,
+    TokenCreated,
+    TokenRevoked,
+    KeysRotated,
+    RoleCreated,
+    RoleUpdated,
+    RoleDeleted,
+    RoleChanged,
+    UserSuspended,
+    UserBanned,
+    SanctionsLifted,
+    PostModerated,
+}
+
+impl Event {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Event::Login => "login",
+            Event::Logout => "logout",
+            Event::RefreshTokenReused => "refresh_token_reused",
+            Event::SessionRevoked => "session_revoked",
+            Event::PasswordReset => "password_reset",
+            Event::PasswordChanged => "password_changed",
+            Event::EmailChangeRequested => "email_change_requested",
+            Event::AccountDeleted => "account_deleted",
+            Event::TwoFactorEnabled => "two_factor_enabled",
+            Event::TwoFactorDisabled => "two_factor_disabled",
+            Event::RecoveryCodesRegenerated => "recovery_codes_regenerated",
+            Event::TokenCreated => "token_created",
+            Event::TokenRevoked => "token_revoked",
+            Event::KeysRotated => "keys_rotated",
+            Event::RoleCreated => "role_created",
+            Event::RoleUpdated => "role_updated",
+            Event::RoleDeleted => "role_deleted",
+            Event::RoleChanged => "role_changed",
+            Event::UserSuspended => "user_suspended",
+            Event::UserBanned => "user_banned",
+            Event::SanctionsLifted => "sanctions_lifted",
+            Event::PostModerated => "post_moderated",
+        }
+    }
+}
+
+pub const OUTCOME_SUCCESS: &str = "success";
+pub const OUTCOME_FAILURE: &str = "failure";
+
+/// Audit record under construction
+///
+/// The actor is who performed the action, the target is the account it affected
+pub struct Entry {
+    event: Event,
+    outcome: &'static str,
+    actor_id: Option<i32>,
+    target_id: Option<i32>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    detail: Option<String>,
+}
+
+impl Entry {
+    pub fn success(event: Event) -> Self {
+        Self::new(event, OUTCOME_SUCCESS)
+    }
+
+    pub fn failure(event: Event) -> Self {
+        Self::new(event, OUTCOME_FAILURE)
+    }
+
+    fn new(event: Event, outcome: &'static str) -> Self {
+        Self {
+            event,
+            outcome,
+            actor_id: None,
+            target_id: None,
+            ip: None,
+            user_agent: None,
+            detail: None,
+        }
+    }
+
+    pub fn actor(mut self, user_id: i32) -> Self {
+        self.actor_id = Some(user_id);
+        self
+    }
+
+    pub fn target(mut self, user_id: i32) -> Self {
+        self.target_id = Some(user_id);
+        self
+    }
+
+    pub fn client(mut self, client: &ClientInfo) -> Self {
+        self.ip = client.ip.clone();
+        self.user_agent = client.user_agent.clone();
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Appends the entry to the audit log
+///
+/// A failed write is logged, but never fails the request it describes
+pub async fn record<C: ConnectionTrait>(db: &C, entry: Entry) {
+    let event = audit_event::ActiveModel {
+        event: Set(entry.event.as_str().to_owned()),
+        outcome: Set(entry.outcome.to_owned()),
+        actor_id: Set(entry.actor_id),
+        target_id: Set(entry.target_id),
+        ip: Set(entry.ip),
+        user_agent: Set(entry.user_agent),
+        detail: Set(entry.detail),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    };
+
+    if let Err(e) = event.insert(db).await {
+        log::error!("Failed to record audit event {}: {e}", entry.event.as_str());
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::OnceLock;
+
+use subtle::ConstantTimeEq;
+use warp::{
+    http::{HeaderValue, Method},
+    hyper::{
+        header::{COOKIE, SET_COOKIE},
+        HeaderMap,
+    },
+    Reply,
+};
+
+use crate::{
+    jwt::{REFRESH_TOKEN_LIFETIME, TOKEN_LIFETIME},
+    models::session::{AuthTokens, CookieSession},
+    tokens,
+};
+
+pub const ACCESS_COOKIE: &str = "nova_access";
+pub const REFRESH_COOKIE: &str = "nova_refresh";
+/// Readable by the frontend, which repeats it in the `X-CSRF-Token` header
+pub const CSRF_COOKIE: &str = "nova_csrf";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// The access token is needed by every API route,
+/// the refresh token only by the routes exchanging and revoking it
+const ACCESS_PATH: &str = "/api";
+const REFRESH_PATH: &str = "/api/auth";
+/// Where refresh cookies of older sessions were scoped to, they are expired on logout
+const LEGACY_REFRESH_PATH: &str = "/api/auth/refresh";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+struct Config {
+    enabled: bool,
+    same_site: &'static str,
+}
+
+/// Cookie sessions are off unless `SESSION_COOKIES` is `true`,
+/// `SESSION_COOKIE_SAMESITE` is `Strict` (default), `Lax` or `None`
+fn config() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        let enabled = std::env::var("SESSION_COOKIES").is_ok_and(|v| v == "true");
+        let same_site = match std::env::var("SESSION_COOKIE_SAMESITE").as_deref() {
+            Ok("Lax") => "Lax",
+            Ok("None") => "None",
+            _ => "Strict",
+        };
+
+        Config { enabled, same_site }
+    })
+}
+
+/// Whether clients can ask for cookie sessions
+pub fn enabled() -> bool {
+    config().enabled
+}
+
+/// Reply to a login or refresh of a cookie session, the tokens go into
+/// `HttpOnly` cookies and only the CSRF token is in the body
+pub fn session_reply(tokens: AuthTokens) -> warp::reply::Response {
+    let csrf_token = tokens::generate();
+
+    let cookies = [
+        cookie(
+            ACCESS_COOKIE,
+            &tokens.access_token,
+            ACCESS_PATH,
+            TOKEN_LIFETIME,
+            true,
+        ),
+        cookie(
+            REFRESH_COOKIE,
+            &tokens.refresh_token,
+            REFRESH_PATH,
+            REFRESH_TOKEN_LIFETIME,
+            true,
+        ),
+        cookie(CSRF_COOKIE, &csrf_token, "/", REFRESH_TOKEN_LIFETIME, false),
+    ];
+
+    let mut response = warp::reply::json(&CookieSession {
+        csrf_token,
+        token_type: "cookie".to_owned(),
+        expires_in: tokens.expires_in,
+    })
+    .into_response();
+    append(&mut response, &cookies);
+
+    response
+}
+
+/// Expires every session cookie in the browser
+pub fn clear(response: &mut warp::reply::Response) {
+    let cookies = [
+        cookie(ACCESS_COOKIE, "", ACCESS_PATH, 0, true),
+        cookie(REFRESH_COOKIE, "", REFRESH_PATH, 0, true),
+        cookie(REFRESH_COOKIE, "", LEGACY_REFRESH_PATH, 0, true),
+        cookie(CSRF_COOKIE, "", "/", 0, false),
+    ];
+    append(response, &cookies);
+}
+
+/// Value of a session cookie, `None` while cookie sessions are disabled
+pub fn read(headers: &HeaderMap<HeaderValue>, name: &str) -> Option<String> {
+    if !enabled() {
+        return None;
+    }
+
+    find(headers, name)
+}
+
+fn find(headers: &HeaderMap<HeaderValue>, name: &str) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+/// Safe methods do not change state, so they are sent without a CSRF token
+pub fn needs_csrf(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Double-submit check, the header has to repeat the CSRF cookie
+///
+/// Other sites can make the browser send the cookie, but can not read it
+pub fn csrf_valid(headers: &HeaderMap<HeaderValue>) -> bool {
+    enabled() && csrf_matches(headers)
+}
+
+fn csrf_matches(headers: &HeaderMap<HeaderValue>) -> bool {
+    let cookie = match find(headers, CSRF_COOKIE) {
+        Some(cookie) => cookie,
+        None => return false,
+    };
+
+    headers
+        .get(CSRF_HEADER)
+        .is_some_and(|header| bool::from(header.as_bytes().ct_eq(cookie.as_bytes())))
+}
+
+fn cookie(name: &str, value: &str, path: &str, max_age: i64, http_only: bool) -> String {
+    let http_only = match http_only {
+        true => "; HttpOnly",
+        false => "",
+    };
+
+    format!(
+        "{name}={value}; Path={path}; Max-Age={max_age}; Secure; SameSite={}{http_only}",
+        config().same_site
+    )
+}
+
+fn append(response: &mut warp::reply::Response, cookies: &[String]) {
+    for cookie in cookies {
+        if let Ok(value) = HeaderValue::from_str(cookie) {
+            response.headers_mut().append(SET_COOKIE, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(cookie: Option<&str>, csrf: Option<&str>) -> HeaderMap<HeaderValue> {
+        let mut headers = HeaderMap::new();
+        if let Some(cookie) = cookie {
+            headers.insert(COOKIE, HeaderValue::from_str(cookie).unwrap());
+        }
+        if let Some(csrf) = csrf {
+            headers.insert(CSRF_HEADER, HeaderValue::from_str(csrf).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn finds_cookie_among_others() {
+        let headers = headers(Some("theme=dark; nova_access=abc; nova_csrf=xyz"), None);
+
+        assert_eq!(find(&headers, ACCESS_COOKIE).as_deref(), Some("abc"));
+        assert_eq!(find(&headers, CSRF_COOKIE).as_deref(), Some("xyz"));
+        assert_eq!(find(&headers, REFRESH_COOKIE), None);
+    }
+
+    #[test]
+    fn ignores_empty_cookie() {
+        let headers = headers(Some("nova_access="), None);
+
+        assert_eq!(find(&headers, ACCESS_COOKIE), None);
+    }
+
+    #[test]
+    fn accepts_matching_csrf_header() {
+        let headers = headers(Some("nova_csrf=xyz"), Some("xyz"));
+
+        assert!(csrf_matches(&headers));
+    }
+
+    #[test]
+    fn rejects_missing_csrf_header() {
+        let headers = headers(Some("nova_csrf=xyz"), None);
+
+        assert!(!csrf_matches(&headers));
+    }
+
+    #[test]
+    fn rejects_mismatched_csrf_header() {
+        let headers = headers(Some("nova_csrf=xyz"), Some("xy"));
+
+        assert!(!csrf_matches(&headers));
+    }
+
+    #[test]
+    fn rejects_csrf_header_without_cookie() {
+        let headers = headers(Some("nova_access=abc"), Some("xyz"));
+
+        assert!(!csrf_matches(&headers));
+    }
+
+    #[test]
+    fn nothing_is_read_while_disabled() {
+        // no test enables cookie sessions, so the configuration keeps them off
+        let headers = headers(Some("nova_access=abc; nova_csrf=xyz"), Some("xyz"));
+
+        assert!(!enabled());
+        assert_eq!(read(&headers, ACCESS_COOKIE), None);
+        assert!(!csrf_valid(&headers));
+    }
+}
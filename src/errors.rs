@@ -1,30 +1,81 @@
 use std::convert::Infallible;
 
 use serde::Serialize;
-use warp::{hyper::StatusCode, Rejection, Reply};
+use warp::{
+    http::{header::RETRY_AFTER, HeaderValue},
+    hyper::StatusCode,
+    Rejection, Reply,
+};
 
-use self::jwt::JWTError;
+use self::{
+    access::AccessError,
+    jwt::JWTError,
+    media::MediaError,
+    throttle::ThrottleError,
+    validation::{FieldError, ValidationError},
+};
 
-pub mod jwt;
+pub mod access;
 pub mod db;
+pub mod export;
+pub mod jwt;
+pub mod mail;
+pub mod media;
+pub mod password;
+pub mod storage;
+pub mod throttle;
+pub mod totp;
+pub mod validation;
 
 #[derive(Serialize)]
 struct ErrorResponse {
     pub message: String,
     pub status: String,
+    /// Broken rules of the request body, one per field
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
 }
 
 pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
+    let mut retry_after = None;
+    let mut errors = Vec::new();
+
     let (code, message) = if err.is_not_found() {
         (StatusCode::NOT_FOUND, "Not Found".to_string())
     } else if let Some(e) = err.find::<JWTError>() {
         match e {
             JWTError::WrongCredentialsError => (StatusCode::FORBIDDEN, e.to_string()),
             JWTError::JWTTokenError => (StatusCode::UNAUTHORIZED, e.to_string()),
-            JWTError::NoPermissionError => (StatusCode::UNAUTHORIZED, e.to_string()),
+            JWTError::NoPermissionError => (StatusCode::FORBIDDEN, e.to_string()),
+            JWTError::MissingScopeError => (StatusCode::FORBIDDEN, e.to_string()),
+            JWTError::AccountSanctionedError => (StatusCode::FORBIDDEN, e.to_string()),
+            JWTError::CsrfTokenError => (StatusCode::FORBIDDEN, e.to_string()),
             JWTError::JWTTokenCreationError => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            JWTError::KeyRotationError => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            JWTError::KeyRotationUnsupportedError => (StatusCode::CONFLICT, e.to_string()),
             _ => (StatusCode::BAD_REQUEST, e.to_string()),
         }
+    } else if let Some(e) = err.find::<AccessError>() {
+        (StatusCode::FORBIDDEN, e.to_string())
+    } else if let Some(e) = err.find::<ValidationError>() {
+        errors = e.errors.clone();
+        (StatusCode::UNPROCESSABLE_ENTITY, e.to_string())
+    } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = err.find::<MediaError>() {
+        match e {
+            MediaError::TooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, e.to_string()),
+            MediaError::UnsupportedType => (StatusCode::UNSUPPORTED_MEDIA_TYPE, e.to_string()),
+            MediaError::Animated => (StatusCode::UNSUPPORTED_MEDIA_TYPE, e.to_string()),
+            MediaError::InvalidImage(_) => (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()),
+            MediaError::Form(_) => (StatusCode::BAD_REQUEST, e.to_string()),
+            MediaError::Task => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    } else if let Some(e) = err.find::<warp::reject::PayloadTooLarge>() {
+        (StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+    } else if let Some(e) = err.find::<ThrottleError>() {
+        retry_after = e.retry_after();
+        (StatusCode::TOO_MANY_REQUESTS, e.to_string())
     } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
         (
             StatusCode::METHOD_NOT_ALLOWED,
@@ -37,10 +88,27 @@ pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible>
         )
     };
 
+    let mut response = error_reply(code, message, errors);
+    if let Some(seconds) = retry_after {
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(seconds));
+    }
+
+    Ok(response)
+}
+
+/// Error body shared by rejections and handlers answering with field errors themselves
+pub fn error_reply(
+    code: StatusCode,
+    message: String,
+    errors: Vec<FieldError>,
+) -> warp::reply::Response {
     let response = warp::reply::json(&ErrorResponse {
-        message: message,
+        message,
         status: code.to_string(),
+        errors,
     });
 
-    Ok(warp::reply::with_status(response, code))
+    warp::reply::with_status(response, code).into_response()
 }
@@ -0,0 +1,12 @@
+use thiserror::Error;
+use warp::reject::Reject;
+
+#[derive(Debug, Error)]
+pub enum AccessError {
+    #[error("Only the author can change this post")]
+    NotAuthor,
+    #[error("The edit window of this post has closed")]
+    EditWindowClosed,
+}
+
+impl Reject for AccessError {}
@@ -8,5 +8,9 @@ pub enum DbError {
     FailedToConvertRow,
     FailedToAdd,
     NotFound,
+    EmailNotVerified,
+    AccountSanctioned,
+    /// Admins acting on their own account
+    OwnAccount,
     InternalError,
 }
@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("Failed to read the data: {0}")]
+    Db(#[from] sea_orm::DbErr),
+    #[error("Failed to serialize the data: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Failed to write the archive: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to write the archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Export task failed")]
+    Task,
+}
@@ -1,7 +1,7 @@
 use thiserror::Error;
 use warp::reject::Reject;
 
-#[allow(unused)]
+#[allow(unused, clippy::enum_variant_names)]
 #[derive(Debug, Error)]
 pub enum JWTError {
     #[error("Wrong Credentials")]
@@ -16,6 +16,16 @@ pub enum JWTError {
     InvalidAuthHeaderError,
     #[error("No Permission")]
     NoPermissionError,
+    #[error("Account is suspended or banned")]
+    AccountSanctionedError,
+    #[error("Token lacks the required scope")]
+    MissingScopeError,
+    #[error("Missing or wrong CSRF token")]
+    CsrfTokenError,
+    #[error("Key rotation Error")]
+    KeyRotationError,
+    #[error("Keys of this algorithm are rotated through the configuration")]
+    KeyRotationUnsupportedError,
 }
 
 impl Reject for JWTError {}
@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error("Invalid mail address: {0}")]
+    Address(String),
+    #[error("Failed to build the mail")]
+    Build,
+    #[error("Failed to deliver the mail: {0}")]
+    Delivery(String),
+}
@@ -0,0 +1,20 @@
+use thiserror::Error;
+use warp::reject::Reject;
+
+#[derive(Debug, Error)]
+pub enum MediaError {
+    #[error("The file is larger than {0} bytes")]
+    TooLarge(usize),
+    #[error("Only JPEG, PNG, GIF and WebP images are accepted")]
+    UnsupportedType,
+    #[error("Animated images are not accepted")]
+    Animated,
+    #[error("The image can not be read: {0}")]
+    InvalidImage(String),
+    #[error("The upload can not be read: {0}")]
+    Form(String),
+    #[error("Processing the image failed")]
+    Task,
+}
+
+impl Reject for MediaError {}
@@ -0,0 +1,9 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PasswordError {
+    #[error("Password hashing failed")]
+    Hashing,
+    #[error("Password task was interrupted")]
+    Task,
+}
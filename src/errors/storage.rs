@@ -0,0 +1,9 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Failed to access the file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("S3 request failed: {0}")]
+    S3(String),
+}
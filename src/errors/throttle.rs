@@ -0,0 +1,25 @@
+use thiserror::Error;
+use warp::reject::Reject;
+
+#[derive(Debug, Error)]
+pub enum ThrottleError {
+    #[error("Too many requests")]
+    TooManyRequests { retry_after: u64 },
+    #[error("Too many failed logins, the account is temporarily locked")]
+    AccountLocked { retry_after: u64 },
+    #[error("Throttle store failed: {0}")]
+    Store(String),
+}
+
+impl ThrottleError {
+    /// Seconds the client has to wait, sent as `Retry-After`
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            ThrottleError::TooManyRequests { retry_after }
+            | ThrottleError::AccountLocked { retry_after } => Some(*retry_after),
+            ThrottleError::Store(_) => None,
+        }
+    }
+}
+
+impl Reject for ThrottleError {}
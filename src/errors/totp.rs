@@ -0,0 +1,9 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TotpError {
+    #[error("Stored TOTP secret is malformed")]
+    MalformedSecret,
+    #[error("Failed to set up TOTP: {0}")]
+    Setup(String),
+}
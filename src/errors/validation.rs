@@ -0,0 +1,20 @@
+use serde::Serialize;
+use thiserror::Error;
+use warp::reject::Reject;
+
+/// Rule a field of a request broke
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    /// Stable name of the rule for clients, e.g. `required` or `too_long`
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Error)]
+#[error("Validation failed")]
+pub struct ValidationError {
+    pub errors: Vec<FieldError>,
+}
+
+impl Reject for ValidationError {}
@@ -0,0 +1,303 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use chrono::Utc;
+use futures::{pin_mut, Stream, TryStreamExt};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    Set,
+};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use entity::{data_export, follower, post, post_like, session, user};
+
+use crate::{
+    errors::export::ExportError,
+    models::export::{ExportedFollow, ExportedLike, ExportedProfile, ExportedSession},
+};
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_READY: &str = "ready";
+pub const STATUS_FAILED: &str = "failed";
+
+/// How long a finished archive can be downloaded, in seconds
+const EXPORT_LIFETIME: i64 = 60 * 60 * 24 * 7;
+
+/// How often expired archives are removed
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Chunks buffered between the query and the archive writer
+const CHANNEL_CAPACITY: usize = 64;
+
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Part of the archive, handed from the query to the blocking writer
+enum Chunk {
+    File(&'static str),
+    Data(Vec<u8>),
+}
+
+/// Creates `EXPORT_DIR`, `./exports` by default, and fails the jobs
+/// a previous run of the server did not finish
+pub async fn init(db: &DatabaseConnection) -> Result<(), ExportError> {
+    let dir = std::env::var("EXPORT_DIR").unwrap_or_else(|_| "./exports".to_owned());
+    std::fs::create_dir_all(&dir)?;
+    let _ = DIR.set(PathBuf::from(dir));
+
+    let now = Utc::now().naive_utc();
+    data_export::Entity::update_many()
+        .col_expr(data_export::Column::Status, STATUS_FAILED.into())
+        .col_expr(data_export::Column::FinishedAt, now.into())
+        .col_expr(
+            data_export::Column::ExpiresAt,
+            (now + chrono::Duration::seconds(EXPORT_LIFETIME)).into(),
+        )
+        .filter(data_export::Column::Status.is_in([STATUS_PENDING, STATUS_RUNNING]))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Periodically removes expired archives
+pub fn watch(db: DatabaseConnection) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = purge(&db).await {
+                log::error!("Failed to purge expired exports: {e}");
+            }
+        }
+    });
+}
+
+/// Builds the archive of the export in the background
+pub fn start(db: DatabaseConnection, export_id: i32, user_id: i32) {
+    tokio::spawn(async move {
+        let path = DIR
+            .get()
+            .expect("Exports are not initialized")
+            .join(format!("export-{export_id}-{}.zip", Utc::now().timestamp()));
+
+        if let Err(e) = set_status(&db, export_id, STATUS_RUNNING, None).await {
+            log::error!("Failed to start export {export_id}: {e}");
+            return;
+        }
+
+        let result = match build(&db, user_id, path.clone()).await {
+            Ok(_) => set_status(&db, export_id, STATUS_READY, Some(&path)).await,
+            Err(e) => {
+                log::error!("Failed to build export {export_id}: {e}");
+                let _ = tokio::fs::remove_file(&path).await;
+                set_status(&db, export_id, STATUS_FAILED, None).await
+            }
+        };
+
+        if let Err(e) = result {
+            log::error!("Failed to finish export {export_id}: {e}");
+        }
+    });
+}
+
+async fn set_status(
+    db: &DatabaseConnection,
+    export_id: i32,
+    status: &str,
+    path: Option<&Path>,
+) -> Result<(), DbErr> {
+    let now = Utc::now().naive_utc();
+
+    let mut export = data_export::ActiveModel {
+        id: Set(export_id),
+        status: Set(status.to_owned()),
+        ..Default::default()
+    };
+    // failed jobs stay visible as long as an archive would
+    if status == STATUS_READY || status == STATUS_FAILED {
+        export.finished_at = Set(Some(now));
+        export.expires_at = Set(Some(now + chrono::Duration::seconds(EXPORT_LIFETIME)));
+    }
+    if let Some(path) = path {
+        export.file_path = Set(Some(path.to_string_lossy().into_owned()));
+    }
+
+    export.update(db).await.map(|_| ())
+}
+
+/// Streams the rows into the channel while a blocking task writes them into the archive
+async fn build(db: &DatabaseConnection, user_id: i32, path: PathBuf) -> Result<(), ExportError> {
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let writer = tokio::task::spawn_blocking(move || write_archive(&path, rx));
+
+    let produced = produce(db, user_id, &tx).await;
+    drop(tx);
+
+    // a failed writer also stops the producer, its error is the one worth reporting
+    writer.await.map_err(|_| ExportError::Task)??;
+    produced
+}
+
+async fn produce(
+    db: &DatabaseConnection,
+    user_id: i32,
+    tx: &mpsc::Sender<Chunk>,
+) -> Result<(), ExportError> {
+    let user = user::Entity::find_by_id(user_id)
+        .one(db)
+        .await?
+        .ok_or(DbErr::RecordNotFound(format!("user {user_id}")))?;
+
+    let profile = ExportedProfile {
+        id: user.id,
+        username: user.username,
+        email: user.email,
+        display_name: user.display_name,
+        bio: user.bio,
+        location: user.location,
+        website: user.website,
+        avatar_url: user.avatar_url,
+        banner_url: user.banner_url,
+        followers: user.followers,
+        following: user.following,
+        created_at: user.created_at,
+        email_verified_at: user.email_verified_at,
+    };
+    send(tx, Chunk::File("profile.json")).await?;
+    send(tx, Chunk::Data(serde_json::to_vec_pretty(&profile)?)).await?;
+
+    let posts = post::Entity::find()
+        .filter(post::Column::UserId.eq(user_id))
+        .filter(post::Column::DeletedAt.is_null())
+        .order_by_asc(post::Column::Id)
+        .stream(db)
+        .await?;
+    write_array(tx, "posts.json", posts, |post| post).await?;
+
+    let likes = post_like::Entity::find()
+        .filter(post_like::Column::UserId.eq(user_id))
+        .order_by_asc(post_like::Column::CreatedAt)
+        .stream(db)
+        .await?;
+    write_array(tx, "likes.json", likes, |like| ExportedLike {
+        post_id: like.post_id,
+        created_at: like.created_at,
+    })
+    .await?;
+
+    let followers = follower::Entity::find()
+        .filter(follower::Column::UserId.eq(user_id))
+        .order_by_asc(follower::Column::CreatedAt)
+        .stream(db)
+        .await?;
+    write_array(tx, "followers.json", followers, |edge| ExportedFollow {
+        user_id: edge.follower_id,
+        created_at: edge.created_at,
+    })
+    .await?;
+
+    let following = follower::Entity::find()
+        .filter(follower::Column::FollowerId.eq(user_id))
+        .order_by_asc(follower::Column::CreatedAt)
+        .stream(db)
+        .await?;
+    write_array(tx, "following.json", following, |edge| ExportedFollow {
+        user_id: edge.user_id,
+        created_at: edge.created_at,
+    })
+    .await?;
+
+    let sessions = session::Entity::find()
+        .filter(session::Column::UserId.eq(user_id))
+        .order_by_asc(session::Column::CreatedAt)
+        .stream(db)
+        .await?;
+    write_array(tx, "sessions.json", sessions, |session| ExportedSession {
+        id: session.id,
+        user_agent: session.user_agent,
+        ip: session.ip,
+        created_at: session.created_at,
+        last_used_at: session.last_used_at,
+        revoked_at: session.revoked_at,
+    })
+    .await
+}
+
+/// Writes the rows as a JSON array, one row at a time
+async fn write_array<M, T, S>(
+    tx: &mpsc::Sender<Chunk>,
+    name: &'static str,
+    rows: S,
+    map: impl Fn(M) -> T,
+) -> Result<(), ExportError>
+where
+    T: Serialize,
+    S: Stream<Item = Result<M, DbErr>>,
+{
+    pin_mut!(rows);
+
+    send(tx, Chunk::File(name)).await?;
+    send(tx, Chunk::Data(b"[".to_vec())).await?;
+
+    let mut first = true;
+    while let Some(row) = rows.try_next().await? {
+        let mut data = match first {
+            true => b"\n".to_vec(),
+            false => b",\n".to_vec(),
+        };
+        serde_json::to_writer(&mut data, &map(row))?;
+        send(tx, Chunk::Data(data)).await?;
+        first = false;
+    }
+
+    send(tx, Chunk::Data(b"\n]\n".to_vec())).await
+}
+
+async fn send(tx: &mpsc::Sender<Chunk>, chunk: Chunk) -> Result<(), ExportError> {
+    // only fails once the writer gave up
+    tx.send(chunk).await.map_err(|_| ExportError::Task)
+}
+
+fn write_archive(path: &Path, mut rx: mpsc::Receiver<Chunk>) -> Result<(), ExportError> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    while let Some(chunk) = rx.blocking_recv() {
+        match chunk {
+            Chunk::File(name) => zip.start_file(name, options)?,
+            Chunk::Data(data) => zip.write_all(&data)?,
+        }
+    }
+
+    zip.finish()?.sync_all()?;
+    Ok(())
+}
+
+async fn purge(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let expired = data_export::Entity::find()
+        .filter(data_export::Column::ExpiresAt.lt(Utc::now().naive_utc()))
+        .all(db)
+        .await?;
+
+    for export in expired {
+        if let Some(path) = &export.file_path {
+            if let Err(e) = tokio::fs::remove_file(path).await {
+                log::warn!("Failed to remove export archive {path}: {e}");
+            }
+        }
+        data_export::Entity::delete_by_id(export.id)
+            .exec(db)
+            .await?;
+    }
+
+    Ok(())
+}
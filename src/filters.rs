@@ -1,16 +1,28 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use sea_orm::DatabaseConnection;
 use tokio::sync::Mutex;
-use warp::{header::headers_cloned, http::HeaderValue, hyper::HeaderMap, Filter, Rejection};
+use warp::{
+    header::headers_cloned,
+    http::{HeaderValue, Method},
+    hyper::HeaderMap,
+    Filter, Rejection,
+};
 
-use crate::models::role::Role;
+use crate::{
+    jwt::Claims,
+    models::{actor::Actor, permission::Permission, session::ClientInfo},
+    throttle,
+};
 
-use self::auth::authorize;
+use self::auth::{authorize, authorize_claims};
 
+pub mod admin;
 pub mod auth;
-pub mod users;
+pub mod media;
 pub mod posts;
+pub mod users;
+pub mod well_known;
 
 pub fn with_session(
     session: Arc<Mutex<DatabaseConnection>>,
@@ -19,12 +31,60 @@ pub fn with_session(
     warp::any().map(move || session.clone())
 }
 
+/// Extracts the user id of an access token or a personal access token
+/// granted the permission
 pub fn with_auth(
     session: Arc<Mutex<DatabaseConnection>>,
-    role: Role,
+    permission: Permission,
 ) -> impl Filter<Extract = (i32,), Error = Rejection> + Clone {
-    headers_cloned()
-        .map(move |headers: HeaderMap<HeaderValue>| (role.clone(), headers))
+    warp::method()
+        .and(headers_cloned())
+        .map(move |method: Method, headers: HeaderMap<HeaderValue>| (permission, method, headers))
+        .and(with_session(session))
+        .and_then(authorize)
+        .map(|actor: Actor| actor.id)
+}
+
+/// Extracts the caller of an access token or a personal access token
+/// granted the permission, along with every permission of the token
+pub fn with_actor(
+    session: Arc<Mutex<DatabaseConnection>>,
+    permission: Permission,
+) -> impl Filter<Extract = (Actor,), Error = Rejection> + Clone {
+    warp::method()
+        .and(headers_cloned())
+        .map(move |method: Method, headers: HeaderMap<HeaderValue>| (permission, method, headers))
         .and(with_session(session))
         .and_then(authorize)
 }
+
+/// Extracts all claims of an access token, personal access tokens are refused
+pub fn with_claims(
+    session: Arc<Mutex<DatabaseConnection>>,
+    permission: Permission,
+) -> impl Filter<Extract = (Claims,), Error = Rejection> + Clone {
+    warp::method()
+        .and(headers_cloned())
+        .map(move |method: Method, headers: HeaderMap<HeaderValue>| (permission, method, headers))
+        .and(with_session(session))
+        .and_then(authorize_claims)
+}
+
+/// Address and user agent of the client
+pub fn with_client() -> impl Filter<Extract = (ClientInfo,), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and(warp::header::optional::<String>("user-agent"))
+        .map(
+            |addr: Option<SocketAddr>, user_agent: Option<String>| ClientInfo {
+                ip: addr.map(|addr| addr.ip().to_string()),
+                user_agent,
+            },
+        )
+}
+
+/// Takes a token from the auth bucket of the client, answers 429 when it is empty
+pub fn with_throttle() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and_then(throttle::check_client)
+        .untuple_one()
+}
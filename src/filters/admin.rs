@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use sea_orm::DatabaseConnection;
+use tokio::sync::Mutex;
+use warp::Filter;
+
+use crate::{
+    handlers,
+    models::permission::Permission,
+    requests::admin::{
+        AuditQuery, RoleChangeRequest, RoleCreateRequest, RolePermissionsRequest, SanctionRequest,
+        SuspendRequest,
+    },
+    validation,
+};
+
+use super::{with_claims, with_client, with_session};
+
+// All admin routes
+pub fn admin(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("admin").and(
+        rotate_keys(session.clone())
+            .or(permissions_list(session.clone()))
+            .or(roles_list(session.clone()))
+            .or(roles_create(session.clone()))
+            .or(roles_update(session.clone()))
+            .or(roles_delete(session.clone()))
+            .or(users_get(session.clone()))
+            .or(users_role(session.clone()))
+            .or(users_suspend(session.clone()))
+            .or(users_ban(session.clone()))
+            .or(users_lift(session.clone()))
+            .or(audit_list(session)),
+    )
+}
+
+/// POST /admin/keys/rotate
+pub fn rotate_keys(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("keys" / "rotate")
+        .and(warp::post())
+        .and(with_claims(session.clone(), Permission::KeysRotate))
+        .and(with_client())
+        .and(with_session(session))
+        .and_then(handlers::admin::rotate_keys)
+}
+
+/// GET /admin/permissions
+pub fn permissions_list(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("permissions")
+        .and(warp::get())
+        .and(with_claims(session.clone(), Permission::RolesManage))
+        .and(with_session(session))
+        .and_then(handlers::admin::list_permissions)
+}
+
+/// GET /admin/roles
+pub fn roles_list(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("roles")
+        .and(warp::get())
+        .and(with_claims(session.clone(), Permission::RolesManage))
+        .and(with_session(session))
+        .and_then(handlers::admin::list_roles)
+}
+
+/// POST /admin/roles
+pub fn roles_create(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("roles")
+        .and(warp::post())
+        .and(with_claims(session.clone(), Permission::RolesManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_role())
+        .and_then(handlers::admin::create_role)
+}
+
+/// PUT /admin/roles/:id/permissions
+pub fn roles_update(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("roles" / i32 / "permissions")
+        .and(warp::put())
+        .and(with_claims(session.clone(), Permission::RolesManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_role_permissions())
+        .and_then(handlers::admin::update_role)
+}
+
+/// DELETE /admin/roles/:id
+pub fn roles_delete(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("roles" / i32)
+        .and(warp::delete())
+        .and(with_claims(session.clone(), Permission::RolesManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and_then(handlers::admin::delete_role)
+}
+
+/// GET /admin/users/:id
+pub fn users_get(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / i32)
+        .and(warp::get())
+        .and(with_claims(session.clone(), Permission::UsersManage))
+        .and(with_session(session))
+        .and_then(handlers::admin::get_user)
+}
+
+/// PUT /admin/users/:id/role
+pub fn users_role(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / i32 / "role")
+        .and(warp::put())
+        .and(with_claims(session.clone(), Permission::UsersManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_role_change())
+        .and_then(handlers::admin::change_role)
+}
+
+/// POST /admin/users/:id/suspension
+pub fn users_suspend(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / i32 / "suspension")
+        .and(warp::post())
+        .and(with_claims(session.clone(), Permission::UsersManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_suspend())
+        .and_then(handlers::admin::suspend_user)
+}
+
+/// POST /admin/users/:id/ban
+pub fn users_ban(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / i32 / "ban")
+        .and(warp::post())
+        .and(with_claims(session.clone(), Permission::UsersManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_sanction())
+        .and_then(handlers::admin::ban_user)
+}
+
+/// DELETE /admin/users/:id/sanctions
+pub fn users_lift(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / i32 / "sanctions")
+        .and(warp::delete())
+        .and(with_claims(session.clone(), Permission::UsersManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_sanction())
+        .and_then(handlers::admin::lift_sanctions)
+}
+
+/// GET /admin/audit?user_id=&event=&from=&to=&before=&limit=
+pub fn audit_list(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("audit")
+        .and(warp::get())
+        .and(with_claims(session.clone(), Permission::AuditRead))
+        .and(with_session(session))
+        .and(warp::query::<AuditQuery>())
+        .and_then(handlers::admin::list_audit_events)
+}
+
+fn json_body_role() -> impl Filter<Extract = (RoleCreateRequest,), Error = warp::Rejection> + Clone
+{
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
+fn json_body_role_permissions(
+) -> impl Filter<Extract = (RolePermissionsRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+fn json_body_role_change(
+) -> impl Filter<Extract = (RoleChangeRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
+fn json_body_suspend() -> impl Filter<Extract = (SuspendRequest,), Error = warp::Rejection> + Clone
+{
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
+fn json_body_sanction() -> impl Filter<Extract = (SanctionRequest,), Error = warp::Rejection> + Clone
+{
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
@@ -1,18 +1,30 @@
-use std::{str::FromStr, sync::Arc};
+use std::sync::Arc;
 
-use jsonwebtoken::{decode, Algorithm, DecodingKey, TokenData, Validation};
+use jsonwebtoken::{decode, decode_header, TokenData, Validation};
 use sea_orm::DatabaseConnection;
 use tokio::sync::Mutex;
-use warp::{http::HeaderValue, hyper::HeaderMap, reject, Filter, Rejection};
+use warp::{
+    http::{HeaderValue, Method},
+    hyper::HeaderMap,
+    reject, Filter, Rejection,
+};
 
 use crate::{
-    errors::jwt::JWTError,
-    handlers::{self, auth::validate_session},
-    jwt::{jwt_from_header, Claims},
-    requests::auth::{AuthRequest, LogoutRequest}, models::role::Role,
+    cookies,
+    errors::{db::DbError, jwt::JWTError},
+    handlers::{self, auth::validate_session, personal_tokens},
+    jwt::{jwt_from_request, keys, Claims, TokenSource},
+    models::{actor::Actor, permission::Permission},
+    permissions,
+    requests::auth::{
+        AuthRequest, ForgotPasswordRequest, LogoutRequest, PersonalTokenCreateRequest,
+        RefreshRequest, ResendVerificationRequest, ResetPasswordRequest, TwoFactorCodeRequest,
+        TwoFactorLoginRequest, VerifyQuery,
+    },
+    validation,
 };
 
-use super::with_session;
+use super::{with_claims, with_client, with_session, with_throttle};
 
 // All auth routes
 pub fn auth(
@@ -21,7 +33,23 @@ pub fn auth(
     warp::path("auth").and(
         login(session.clone())
             .or(register(session.clone()))
-            .or(logout(session.clone())),
+            .or(logout(session.clone()))
+            .or(refresh(session.clone()))
+            .or(verify(session.clone()))
+            .or(resend_verification(session.clone()))
+            .or(password_forgot(session.clone()))
+            .or(password_reset(session.clone()))
+            .or(two_factor_enroll(session.clone()))
+            .or(two_factor_confirm(session.clone()))
+            .or(two_factor_recovery_codes(session.clone()))
+            .or(two_factor_disable(session.clone()))
+            .or(two_factor_login(session.clone()))
+            .or(sessions_list(session.clone()))
+            .or(sessions_delete(session.clone()))
+            .or(sessions_delete_others(session.clone()))
+            .or(tokens_list(session.clone()))
+            .or(tokens_create(session.clone()))
+            .or(tokens_revoke(session.clone())),
     )
 }
 
@@ -31,7 +59,9 @@ pub fn login(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("login")
         .and(warp::post())
+        .and(with_throttle())
         .and(with_session(session))
+        .and(with_client())
         .and(json_body())
         .and_then(handlers::auth::login)
 }
@@ -42,8 +72,9 @@ pub fn register(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("register")
         .and(warp::post())
+        .and(with_throttle())
         .and(with_session(session))
-        .and(json_body())
+        .and(json_body_register())
         .and_then(handlers::auth::register)
 }
 
@@ -53,40 +84,310 @@ pub fn logout(
     warp::path!("logout")
         .and(warp::post())
         .and(with_session(session))
+        .and(with_client())
+        .and(warp::header::headers_cloned())
         .and(json_body_logout())
         .and_then(handlers::auth::logout)
 }
 
+/// POST /auth/refresh
+pub fn refresh(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("refresh")
+        .and(warp::post())
+        .and(with_session(session))
+        .and(with_client())
+        .and(warp::header::headers_cloned())
+        .and(json_body_refresh())
+        .and_then(handlers::auth::refresh)
+}
+
+/// GET /auth/verify?token=
+pub fn verify(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("verify")
+        .and(warp::get())
+        .and(with_session(session))
+        .and(warp::query::<VerifyQuery>())
+        .and_then(handlers::verification::verify)
+}
+
+/// POST /auth/verify/resend
+pub fn resend_verification(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("verify" / "resend")
+        .and(warp::post())
+        .and(with_throttle())
+        .and(with_session(session))
+        .and(json_body_resend())
+        .and_then(handlers::verification::resend)
+}
+
+/// POST /auth/password/forgot
+pub fn password_forgot(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("password" / "forgot")
+        .and(warp::post())
+        .and(with_throttle())
+        .and(with_session(session))
+        .and(json_body_forgot())
+        .and_then(handlers::password_reset::forgot)
+}
+
+/// POST /auth/password/reset
+pub fn password_reset(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("password" / "reset")
+        .and(warp::post())
+        .and(with_throttle())
+        .and(with_session(session))
+        .and(with_client())
+        .and(json_body_reset())
+        .and_then(handlers::password_reset::reset)
+}
+
+/// POST /auth/2fa/enroll
+pub fn two_factor_enroll(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("2fa" / "enroll")
+        .and(warp::post())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_session(session))
+        .and_then(handlers::two_factor::enroll)
+}
+
+/// POST /auth/2fa/confirm
+pub fn two_factor_confirm(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("2fa" / "confirm")
+        .and(warp::post())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_two_factor_code())
+        .and_then(handlers::two_factor::confirm)
+}
+
+/// POST /auth/2fa/recovery-codes
+pub fn two_factor_recovery_codes(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("2fa" / "recovery-codes")
+        .and(warp::post())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_two_factor_code())
+        .and_then(handlers::two_factor::regenerate_recovery_codes)
+}
+
+/// DELETE /auth/2fa
+pub fn two_factor_disable(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("2fa")
+        .and(warp::delete())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_two_factor_code())
+        .and_then(handlers::two_factor::disable)
+}
+
+/// POST /auth/2fa/login
+pub fn two_factor_login(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("2fa" / "login")
+        .and(warp::post())
+        .and(with_throttle())
+        .and(with_session(session))
+        .and(with_client())
+        .and(json_body_two_factor_login())
+        .and_then(handlers::two_factor::login)
+}
+
+/// GET /auth/sessions
+pub fn sessions_list(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("sessions")
+        .and(warp::get())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_session(session))
+        .and_then(handlers::auth::list_sessions)
+}
+
+/// DELETE /auth/sessions/:id
+pub fn sessions_delete(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("sessions" / i32)
+        .and(warp::delete())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and_then(handlers::auth::delete_session)
+}
+
+/// DELETE /auth/sessions, logs out everywhere except the current session
+pub fn sessions_delete_others(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("sessions")
+        .and(warp::delete())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and_then(handlers::auth::delete_other_sessions)
+}
+
+/// GET /auth/tokens
+pub fn tokens_list(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("tokens")
+        .and(warp::get())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_session(session))
+        .and_then(handlers::personal_tokens::list)
+}
+
+/// POST /auth/tokens
+pub fn tokens_create(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("tokens")
+        .and(warp::post())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_token())
+        .and_then(handlers::personal_tokens::create)
+}
+
+/// DELETE /auth/tokens/:id
+pub fn tokens_revoke(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("tokens" / i32)
+        .and(warp::delete())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and_then(handlers::personal_tokens::revoke)
+}
+
 fn json_body() -> impl Filter<Extract = (AuthRequest,), Error = warp::Rejection> + Clone {
     // When accepting a body, we want a JSON body
     // (and to reject huge payloads)...
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
+fn json_body_register() -> impl Filter<Extract = (AuthRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
 fn json_body_logout() -> impl Filter<Extract = (LogoutRequest,), Error = warp::Rejection> + Clone {
     warp::body::content_length_limit(1024 * 16).and(warp::body::json())
 }
 
+fn json_body_refresh() -> impl Filter<Extract = (RefreshRequest,), Error = warp::Rejection> + Clone
+{
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+fn json_body_resend(
+) -> impl Filter<Extract = (ResendVerificationRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+fn json_body_two_factor_code(
+) -> impl Filter<Extract = (TwoFactorCodeRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+fn json_body_two_factor_login(
+) -> impl Filter<Extract = (TwoFactorLoginRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+fn json_body_token(
+) -> impl Filter<Extract = (PersonalTokenCreateRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
+fn json_body_forgot(
+) -> impl Filter<Extract = (ForgotPasswordRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+}
+
+fn json_body_reset(
+) -> impl Filter<Extract = (ResetPasswordRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
+/// Accepts access tokens and personal access tokens carrying the permission
 pub async fn authorize(
-    (role, headers): (Role, HeaderMap<HeaderValue>),
-    session: Arc<Mutex<DatabaseConnection>>,
-) -> Result<i32, Rejection> {
-    match jwt_from_header(&headers) {
-        Ok(token) => {
-            let decoded = check_token(session, token)
-                .await
-                .map_err(|_| reject::custom(JWTError::JWTTokenError))?;
-
-            if ![Role::Admin, Role::Moderator]
-                .contains(&Role::from_str(&decoded.claims.role).unwrap())
-                && [Role::Admin, Role::Moderator].contains(&role)
+    (permission, method, headers): (Permission, Method, HeaderMap<HeaderValue>),
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<Actor, Rejection> {
+    let (token, source) = jwt_from_request(&headers).map_err(reject::custom)?;
+
+    // personal access tokens are never put into cookies
+    if source == TokenSource::Header && token.starts_with(personal_tokens::TOKEN_PREFIX) {
+        return personal_tokens::authenticate(session, &token, permission)
+            .await
+            .map_err(reject::custom);
+    }
+
+    authorize_claims((permission, method, headers), session)
+        .await
+        .map(|claims| Actor {
+            id: claims.sub as i32,
+            permissions: permissions::effective(&claims),
+        })
+}
+
+/// Accepts access tokens only, for routes personal access tokens must not reach
+pub async fn authorize_claims(
+    (permission, method, headers): (Permission, Method, HeaderMap<HeaderValue>),
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<Claims, Rejection> {
+    match jwt_from_request(&headers) {
+        Ok((token, source)) => {
+            if source == TokenSource::Cookie
+                && cookies::needs_csrf(&method)
+                && !cookies::csrf_valid(&headers)
             {
+                return Err(reject::custom(JWTError::CsrfTokenError));
+            }
+
+            let decoded = check_token(session, token).await.map_err(|e| match e {
+                JWTError::AccountSanctionedError => reject::custom(e),
+                _ => reject::custom(JWTError::JWTTokenError),
+            })?;
+
+            if !permissions::granted(&decoded.claims, permission) {
                 return Err(reject::custom(JWTError::NoPermissionError));
             }
 
-            Ok(decoded.claims.sub as i32)
+            Ok(decoded.claims)
         }
-        Err(e) => return Err(reject::custom(e)),
+        Err(e) => Err(reject::custom(e)),
     }
 }
 
@@ -94,17 +395,21 @@ pub async fn check_token(
     session: Arc<Mutex<DatabaseConnection>>,
     token: String,
 ) -> Result<TokenData<Claims>, JWTError> {
-    let decoded = decode::<Claims>(
-        &token,
-        &DecodingKey::from_secret(b"123"),
-        &Validation::new(Algorithm::HS512),
-    )
-    .map_err(|_| JWTError::JWTTokenError)?;
+    let kid = decode_header(&token)
+        .map_err(|_| JWTError::JWTTokenError)?
+        .kid
+        .ok_or(JWTError::JWTTokenError)?;
+
+    let db = session.lock().await.to_owned();
+    let (algorithm, key) = keys::decoding_key(&db, &kid).await?;
+
+    let decoded = decode::<Claims>(&token, &key, &Validation::new(algorithm))
+        .map_err(|_| JWTError::JWTTokenError)?;
 
-    // Check tokens
-    let validated = validate_session(session, &token).await;
-    if validated.is_err() {
-        return Err(JWTError::JWTTokenError);
+    // Check the session is still alive and the user not sanctioned
+    match validate_session(session, decoded.claims.sid).await {
+        Ok(_) => Ok(decoded),
+        Err(DbError::AccountSanctioned) => Err(JWTError::AccountSanctionedError),
+        Err(_) => Err(JWTError::JWTTokenError),
     }
-    Ok(decoded)
 }
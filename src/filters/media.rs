@@ -0,0 +1,55 @@
+use sea_orm::DatabaseConnection;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::Filter;
+
+use crate::{handlers, media, models::permission::Permission};
+
+use super::{with_auth, with_session};
+
+pub fn media(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    upload(session.clone())
+        .or(get(session.clone()))
+        .or(get_thumbnail(session.clone()))
+}
+
+/// POST /media
+pub fn upload(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("media")
+        .and(warp::post())
+        .and(with_auth(session.clone(), Permission::PostsWrite))
+        .and(with_session(session))
+        .and(form())
+        .and_then(handlers::media::upload)
+}
+
+/// GET /media/:id
+pub fn get(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("media" / i32)
+        .and(warp::get())
+        .and(with_auth(session.clone(), Permission::PostsRead))
+        .and(with_session(session))
+        .and_then(handlers::media::get)
+}
+
+/// GET /media/:id/thumbnail
+pub fn get_thumbnail(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("media" / i32 / "thumbnail")
+        .and(warp::get())
+        .and(with_auth(session.clone(), Permission::PostsRead))
+        .and(with_session(session))
+        .and_then(handlers::media::get_thumbnail)
+}
+
+fn form() -> impl Filter<Extract = (warp::multipart::FormData,), Error = warp::Rejection> + Clone {
+    // room for the alt text and the multipart framing next to the file
+    warp::multipart::form().max_length((media::max_size() + 1024 * 16) as u64)
+}
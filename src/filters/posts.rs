@@ -3,9 +3,17 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use warp::Filter;
 
-use crate::{handlers, models::role::Role, requests::post::create::PostCreateRequest};
+use crate::{
+    handlers,
+    models::permission::Permission,
+    requests::post::{
+        create::PostCreateRequest,
+        thread::{RepliesQuery, ThreadQuery},
+    },
+    validation,
+};
 
-use super::{with_auth, with_session};
+use super::{with_actor, with_auth, with_client, with_session};
 
 pub fn posts(
     session: Arc<Mutex<DatabaseConnection>>,
@@ -17,6 +25,10 @@ pub fn posts(
         .or(update(session.clone()))
         .or(delete(session.clone()))
         .or(like(session.clone()))
+        .or(repost(session.clone()))
+        .or(list_replies(session.clone()))
+        .or(thread(session.clone()))
+        .or(list_revisions(session.clone()))
 }
 
 pub fn list(
@@ -24,7 +36,7 @@ pub fn list(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("posts")
         .and(warp::get())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::PostsRead))
         .and(with_session(session))
         .and_then(handlers::post::list)
 }
@@ -34,7 +46,7 @@ pub fn list_feed(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("feed")
         .and(warp::get())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::FeedRead))
         .and(with_session(session))
         .and_then(handlers::post::list_feed)
 }
@@ -44,7 +56,7 @@ pub fn create(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("posts")
         .and(warp::post())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::PostsWrite))
         .and(with_session(session))
         .and(json_body())
         .and_then(handlers::post::create)
@@ -55,27 +67,70 @@ pub fn get(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("posts" / i32)
         .and(warp::get())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::PostsRead))
         .and(with_session(session))
         .and_then(handlers::post::get_by_id)
 }
 
+pub fn list_replies(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("posts" / i32 / "replies")
+        .and(warp::get())
+        .and(with_auth(session.clone(), Permission::PostsRead))
+        .and(with_session(session))
+        .and(warp::query::<RepliesQuery>())
+        .and_then(handlers::post::list_replies)
+}
+
+pub fn thread(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("posts" / i32 / "thread")
+        .and(warp::get())
+        .and(with_auth(session.clone(), Permission::PostsRead))
+        .and(with_session(session))
+        .and(warp::query::<ThreadQuery>())
+        .and_then(handlers::post::get_thread)
+}
+
+pub fn list_revisions(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("posts" / i32 / "revisions")
+        .and(warp::get())
+        .and(with_auth(session.clone(), Permission::PostsRead))
+        .and(with_session(session))
+        .and_then(handlers::post::list_revisions)
+}
+
 pub fn like(
     session: Arc<Mutex<DatabaseConnection>>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("posts" / i32 / "like")
         .and(warp::post())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::PostsWrite))
         .and(with_session(session))
         .and_then(handlers::post::like)
 }
 
+pub fn repost(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("posts" / i32 / "repost")
+        .and(warp::post())
+        .and(with_auth(session.clone(), Permission::PostsWrite))
+        .and(with_session(session))
+        .and_then(handlers::post::repost)
+}
+
 pub fn update(
     session: Arc<Mutex<DatabaseConnection>>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("posts" / i32)
         .and(warp::patch())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_actor(session.clone(), Permission::PostsWrite))
+        .and(with_client())
         .and(with_session(session))
         .and(json_body())
         .and_then(handlers::post::update)
@@ -86,7 +141,8 @@ pub fn delete(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("posts" / i32)
         .and(warp::delete())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_actor(session.clone(), Permission::PostsWrite))
+        .and(with_client())
         .and(with_session(session))
         .and_then(handlers::post::delete)
 }
@@ -94,5 +150,7 @@ pub fn delete(
 fn json_body() -> impl Filter<Extract = (PostCreateRequest,), Error = warp::Rejection> + Clone {
     // When accepting a body, we want a JSON body
     // (and to reject huge payloads)...
-    warp::body::content_length_limit(1024 * 16).and(warp::body::json())
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
 }
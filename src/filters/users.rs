@@ -4,9 +4,16 @@ use sea_orm::DatabaseConnection;
 use tokio::sync::Mutex;
 use warp::Filter;
 
-use crate::{handlers, models::role::Role};
+use crate::{
+    handlers,
+    models::permission::Permission,
+    requests::account::{
+        AccountDeleteRequest, EmailChangeRequest, PasswordChangeRequest, ProfileUpdateRequest,
+    },
+    validation,
+};
 
-use super::{with_auth, with_session};
+use super::{with_auth, with_claims, with_client, with_session, with_throttle};
 
 pub fn users(
     session: Arc<Mutex<DatabaseConnection>>,
@@ -17,6 +24,13 @@ pub fn users(
         .or(follow(session.clone()))
         .or(get_user_followers(session.clone()))
         .or(get_user_following(session.clone()))
+        .or(users_update_me(session.clone()))
+        .or(users_change_email(session.clone()))
+        .or(users_change_password(session.clone()))
+        .or(users_delete_me(session.clone()))
+        .or(export_request(session.clone()))
+        .or(export_status(session.clone()))
+        .or(export_download(session.clone()))
     // .or(users_update(session.clone()))
     // .or(users_delete(session))
 }
@@ -28,7 +42,7 @@ pub fn users_list(
     warp::path!("users")
         .and(warp::get())
         .and(with_session(session.clone()))
-        .and(with_auth(session, Role::User))
+        .and(with_auth(session, Permission::UsersRead))
         .and_then(handlers::users::list)
 }
 
@@ -38,7 +52,7 @@ pub fn users_by_username(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("users" / String)
         .and(warp::get())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::UsersRead))
         .and(with_session(session))
         .and_then(handlers::users::get_by_username)
 }
@@ -49,7 +63,7 @@ pub fn users_get_by_id(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("users" / i32)
         .and(warp::get())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::UsersRead))
         .and(with_session(session))
         .and_then(handlers::users::get_by_id)
 }
@@ -60,7 +74,7 @@ pub fn follow(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("users" / i32 / "follow")
         .and(warp::post())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::FollowsWrite))
         .and(with_session(session))
         .and_then(handlers::users::follow)
 }
@@ -71,7 +85,7 @@ pub fn get_user_followers(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("users" / i32 / "followers")
         .and(warp::get())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::FollowsRead))
         .and(with_session(session))
         .and_then(handlers::users::get_user_followers)
 }
@@ -82,11 +96,126 @@ pub fn get_user_following(
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path!("users" / i32 / "following")
         .and(warp::get())
-        .and(with_auth(session.clone(), Role::User))
+        .and(with_auth(session.clone(), Permission::FollowsRead))
         .and(with_session(session))
         .and_then(handlers::users::get_user_following)
 }
 
+/// PATCH /users/me
+pub fn users_update_me(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / "me")
+        .and(warp::patch())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_session(session))
+        .and(json_body_profile())
+        .and_then(handlers::account::update_profile)
+}
+
+/// POST /users/me/email
+pub fn users_change_email(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / "me" / "email")
+        .and(warp::post())
+        .and(with_throttle())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_email_change())
+        .and_then(handlers::account::change_email)
+}
+
+/// POST /users/me/password
+pub fn users_change_password(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / "me" / "password")
+        .and(warp::post())
+        .and(with_throttle())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_password_change())
+        .and_then(handlers::account::change_password)
+}
+
+/// DELETE /users/me
+pub fn users_delete_me(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / "me")
+        .and(warp::delete())
+        .and(with_throttle())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_client())
+        .and(with_session(session))
+        .and(json_body_account_delete())
+        .and_then(handlers::account::delete)
+}
+
+/// POST /users/me/export
+pub fn export_request(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / "me" / "export")
+        .and(warp::post())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_session(session))
+        .and_then(handlers::export::request)
+}
+
+/// GET /users/me/export/:id
+pub fn export_status(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / "me" / "export" / i32)
+        .and(warp::get())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_session(session))
+        .and_then(handlers::export::status)
+}
+
+/// GET /users/me/export/:id/download
+pub fn export_download(
+    session: Arc<Mutex<DatabaseConnection>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("users" / "me" / "export" / i32 / "download")
+        .and(warp::get())
+        .and(with_claims(session.clone(), Permission::AccountManage))
+        .and(with_session(session))
+        .and_then(handlers::export::download)
+}
+
+fn json_body_profile(
+) -> impl Filter<Extract = (ProfileUpdateRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
+fn json_body_email_change(
+) -> impl Filter<Extract = (EmailChangeRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
+fn json_body_password_change(
+) -> impl Filter<Extract = (PasswordChangeRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
+fn json_body_account_delete(
+) -> impl Filter<Extract = (AccountDeleteRequest,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(1024 * 16)
+        .and(warp::body::json())
+        .and_then(validation::validated)
+}
+
 //// POST /users with JSON body
 // pub fn users_create(
 //     session: Arc<Mutex<DatabaseConnection>>,
@@ -0,0 +1,15 @@
+use warp::Filter;
+
+use crate::handlers;
+
+// All public discovery routes
+pub fn well_known() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path(".well-known").and(jwks())
+}
+
+/// GET /.well-known/jwks.json
+pub fn jwks() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("jwks.json")
+        .and(warp::get())
+        .and_then(handlers::well_known::jwks)
+}
@@ -1,3 +1,12 @@
+pub mod account;
+pub mod admin;
 pub mod auth;
+pub mod export;
+pub mod media;
+pub mod password_reset;
+pub mod personal_tokens;
 pub mod post;
+pub mod two_factor;
 pub mod users;
+pub mod verification;
+pub mod well_known;
@@ -0,0 +1,449 @@
+use std::{convert::Infallible, sync::Arc};
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, DbBackend, DbErr, EntityTrait, QueryFilter, Set, Statement,
+    TransactionTrait,
+};
+use tokio::sync::Mutex;
+use warp::{hyper::StatusCode, Reply};
+
+use entity::{data_export, media, password_reset, session, user};
+
+use crate::{
+    audit::{self, Entry, Event},
+    errors::db::DbError,
+    jwt::Claims,
+    media as uploads,
+    models::{session::ClientInfo, user::Profile},
+    password::{self, Verification},
+    requests::account::{
+        AccountDeleteRequest, EmailChangeRequest, PasswordChangeRequest, ProfileUpdateRequest,
+    },
+};
+
+use super::{users::normalize_identifier, verification::send_email_change};
+
+/// What happens to the posts of a deleted account
+///
+/// Selected with `ACCOUNT_DELETION_POSTS`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PostPolicy {
+    /// The posts are deleted along with the account
+    Delete,
+    /// The posts stay, the account is anonymized into a "deleted user"
+    Keep,
+}
+
+fn post_policy() -> PostPolicy {
+    match std::env::var("ACCOUNT_DELETION_POSTS").as_deref() {
+        Ok("keep") => PostPolicy::Keep,
+        _ => PostPolicy::Delete,
+    }
+}
+
+/// Changes the given profile fields
+pub async fn update_profile(
+    claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: ProfileUpdateRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let user = match user::Entity::find_by_id(claims.sub as i32).one(&db).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    let mut user: user::ActiveModel = user.into();
+    if let Some(value) = body.display_name {
+        user.display_name = profile_field(value);
+    }
+    if let Some(value) = body.bio {
+        user.bio = profile_field(value);
+    }
+    if let Some(value) = body.location {
+        user.location = profile_field(value);
+    }
+    if let Some(value) = body.website {
+        user.website = profile_field(value);
+    }
+    if let Some(value) = body.avatar_url {
+        user.avatar_url = profile_field(value);
+    }
+    if let Some(value) = body.banner_url {
+        user.banner_url = profile_field(value);
+    }
+
+    match user.update(&db).await {
+        Ok(user) => Ok(warp::reply::json(&Profile::from(user)).into_response()),
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+/// Trimmed value of a sent profile field, an empty value clears the field
+fn profile_field(value: String) -> ActiveValue<Option<String>> {
+    let value = value.trim().to_string();
+    Set(Some(value).filter(|value| !value.is_empty()))
+}
+
+/// Mails a verification link to the new address, which replaces the current one once opened
+pub async fn change_email(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: EmailChangeRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let email = normalize_identifier(&body.email);
+
+    let db = db_session.lock().await.to_owned();
+
+    let user = match confirm_password(&db, claims.sub as i32, body.password).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let taken = user::Entity::find()
+        .filter(user::Column::Email.eq(&email))
+        .one(&db)
+        .await;
+    match taken {
+        Ok(None) => (),
+        Ok(Some(_)) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&DbError::AlreadyExists),
+                StatusCode::CONFLICT,
+            )
+            .into_response())
+        }
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+
+    let user_id = user.id;
+    let detail = format!("to {email}");
+
+    match send_email_change(&db, &user, email).await {
+        Ok(_) => {
+            let entry = Entry::success(Event::EmailChangeRequested)
+                .actor(user_id)
+                .target(user_id)
+                .client(&client)
+                .detail(detail);
+            audit::record(&db, entry).await;
+
+            Ok(StatusCode::ACCEPTED.into_response())
+        }
+        Err(e) => Ok(warp::reply::with_status(
+            warp::reply::json(&e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .into_response()),
+    }
+}
+
+/// Changes the password and logs out every other session
+pub async fn change_password(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: PasswordChangeRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let user = match confirm_password(&db, claims.sub as i32, body.current_password).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    let password_hash = match password::hash(body.new_password).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            log::error!("Failed to hash password: {e}");
+            return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    let result = async {
+        let txn = db.begin().await?;
+
+        let mut user: user::ActiveModel = user.into();
+        user.password = Set(password_hash);
+        let user = user.update(&txn).await?;
+
+        // pending reset links would undo the change
+        password_reset::Entity::delete_many()
+            .filter(password_reset::Column::UserId.eq(user.id))
+            .exec(&txn)
+            .await?;
+
+        session::Entity::update_many()
+            .col_expr(session::Column::RevokedAt, Utc::now().naive_utc().into())
+            .filter(session::Column::UserId.eq(user.id))
+            .filter(session::Column::Id.ne(claims.sid))
+            .filter(session::Column::RevokedAt.is_null())
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(_) => {
+            let entry = Entry::success(Event::PasswordChanged)
+                .actor(claims.sub as i32)
+                .target(claims.sub as i32)
+                .client(&client);
+            audit::record(&db, entry).await;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+/// Deletes the own account once the password is confirmed
+pub async fn delete(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: AccountDeleteRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let user = match confirm_password(&db, claims.sub as i32, body.password).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
+
+    // archives of exports are removed once the rows are gone
+    let archives = match data_export::Entity::find()
+        .filter(data_export::Column::UserId.eq(user.id))
+        .all(&db)
+        .await
+    {
+        Ok(exports) => exports
+            .into_iter()
+            .filter_map(|export| export.file_path)
+            .collect::<Vec<String>>(),
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    let policy = post_policy();
+
+    // uploads go with the posts, their files once the rows are gone
+    let uploads = match policy {
+        PostPolicy::Delete => match media::Entity::find()
+            .filter(media::Column::UserId.eq(user.id))
+            .all(&db)
+            .await
+        {
+            Ok(uploads) => uploads,
+            Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+        },
+        PostPolicy::Keep => Vec::new(),
+    };
+
+    match delete_account(&db, user.id, policy).await {
+        Ok(_) => {
+            let entry = Entry::success(Event::AccountDeleted)
+                .actor(user.id)
+                .target(user.id)
+                .client(&client)
+                .detail(format!("{policy:?} posts"));
+            audit::record(&db, entry).await;
+
+            for path in archives {
+                if let Err(e) = tokio::fs::remove_file(&path).await {
+                    log::warn!("Failed to remove export archive {path}: {e}");
+                }
+            }
+            uploads::remove_files(&uploads).await;
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Err(e) => {
+            log::error!("Failed to delete account {}: {e}", user.id);
+            Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+async fn delete_account(
+    db: &DatabaseConnection,
+    user_id: i32,
+    policy: PostPolicy,
+) -> Result<(), DbErr> {
+    let txn = db.begin().await?;
+
+    // follow edges, keeping the counters of the other side right
+    execute(
+        &txn,
+        r#"UPDATE "user" SET followers = followers - 1
+        WHERE id IN (SELECT user_id FROM follower WHERE follower_id = $1)"#,
+        user_id,
+    )
+    .await?;
+    execute(
+        &txn,
+        r#"UPDATE "user" SET following = following - 1
+        WHERE id IN (SELECT follower_id FROM follower WHERE user_id = $1)"#,
+        user_id,
+    )
+    .await?;
+    execute(
+        &txn,
+        "DELETE FROM follower WHERE user_id = $1 OR follower_id = $1",
+        user_id,
+    )
+    .await?;
+
+    // likes given, keeping the counters of the liked posts right
+    execute(
+        &txn,
+        r#"UPDATE post SET likes = likes - 1
+        WHERE id IN (SELECT post_id FROM post_like WHERE user_id = $1)"#,
+        user_id,
+    )
+    .await?;
+    execute(&txn, "DELETE FROM post_like WHERE user_id = $1", user_id).await?;
+
+    // credentials and exports, refresh tokens go with their sessions
+    for table in [
+        "session",
+        "personal_access_token",
+        "user_totp",
+        "recovery_code",
+        "login_challenge",
+        "password_reset",
+        "email_verification",
+        "data_export",
+    ] {
+        execute(
+            &txn,
+            &format!("DELETE FROM {table} WHERE user_id = $1"),
+            user_id,
+        )
+        .await?;
+    }
+
+    match policy {
+        PostPolicy::Delete => {
+            execute(
+                &txn,
+                "DELETE FROM post_like WHERE post_id IN (SELECT id FROM post WHERE user_id = $1)",
+                user_id,
+            )
+            .await?;
+            // reposts of other users only point at the deleted posts, they go too
+            execute(
+                &txn,
+                r#"DELETE FROM post WHERE kind = 'repost'
+                AND related_to_post IN (SELECT id FROM post WHERE user_id = $1)"#,
+                user_id,
+            )
+            .await?;
+            // replies and quotes of other users stay, detached from the deleted posts
+            execute(
+                &txn,
+                r#"UPDATE post SET related_to_post = NULL
+                WHERE user_id <> $1
+                AND related_to_post IN (SELECT id FROM post WHERE user_id = $1)"#,
+                user_id,
+            )
+            .await?;
+            // replies, reposts and quotes given, keeping the counters of the other posts right,
+            // deleted ones were not counted anymore
+            execute(
+                &txn,
+                r#"UPDATE post p SET
+                    comments = comments - (
+                        SELECT count(*) FROM post r
+                        WHERE r.related_to_post = p.id AND r.user_id = $1 AND r.kind = 'reply'
+                        AND r.deleted_at IS NULL
+                    ),
+                    reposts = reposts - (
+                        SELECT count(*) FROM post r
+                        WHERE r.related_to_post = p.id AND r.user_id = $1 AND r.kind = 'repost'
+                        AND r.deleted_at IS NULL
+                    ),
+                    quotes = quotes - (
+                        SELECT count(*) FROM post r
+                        WHERE r.related_to_post = p.id AND r.user_id = $1 AND r.kind = 'quote'
+                        AND r.deleted_at IS NULL
+                    )
+                WHERE p.user_id <> $1
+                AND p.id IN (SELECT related_to_post FROM post WHERE user_id = $1)"#,
+                user_id,
+            )
+            .await?;
+            execute(&txn, "DELETE FROM media WHERE user_id = $1", user_id).await?;
+            execute(&txn, "DELETE FROM post WHERE user_id = $1", user_id).await?;
+            execute(&txn, r#"DELETE FROM "user" WHERE id = $1"#, user_id).await?;
+        }
+        PostPolicy::Keep => {
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"UPDATE "user" SET
+                    username = 'deleted-' || id,
+                    email = 'deleted-' || id || '@deleted.invalid',
+                    password = '',
+                    followers = 0,
+                    following = 0,
+                    email_verified_at = NULL,
+                    display_name = NULL,
+                    bio = NULL,
+                    location = NULL,
+                    website = NULL,
+                    avatar_url = NULL,
+                    banner_url = NULL,
+                    deleted_at = $2
+                WHERE id = $1"#,
+                [user_id.into(), Utc::now().naive_utc().into()],
+            ))
+            .await?;
+        }
+    }
+
+    txn.commit().await?;
+
+    log::info!("Deleted account {user_id}, posts policy {policy:?}");
+    Ok(())
+}
+
+async fn execute(txn: &DatabaseTransaction, sql: &str, user_id: i32) -> Result<(), DbErr> {
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        sql,
+        [user_id.into()],
+    ))
+    .await
+    .map(|_| ())
+}
+
+/// Loads the user if the password matches, otherwise the response to answer with
+async fn confirm_password(
+    db: &DatabaseConnection,
+    user_id: i32,
+    password: String,
+) -> Result<user::Model, warp::reply::Response> {
+    let user = match user::Entity::find_by_id(user_id).one(db).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err(StatusCode::NOT_FOUND.into_response()),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    match password::verify(password, user.password.clone()).await {
+        Ok(Verification::Invalid) => Err(warp::reply::with_status(
+            warp::reply::json(&DbError::WrongCredentials),
+            StatusCode::UNAUTHORIZED,
+        )
+        .into_response()),
+        Ok(_) => Ok(user),
+        Err(e) => {
+            log::error!("Failed to verify password: {e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
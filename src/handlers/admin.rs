@@ -0,0 +1,630 @@
+use std::{convert::Infallible, sync::Arc};
+
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde_json::json;
+use tokio::sync::Mutex;
+use warp::{hyper::StatusCode, reject, Rejection, Reply};
+
+use entity::{account_action, audit_event, permission, role, role_permission, user};
+
+use crate::{
+    audit::{self, Entry, Event},
+    errors::db::DbError,
+    jwt::{keys, Claims},
+    models::{
+        audit::AuditEventInfo,
+        permission::{Permission, PermissionInfo},
+        role::RoleInfo,
+        session::ClientInfo,
+        user::{AccountActionInfo, AccountStatus},
+    },
+    permissions,
+    requests::admin::{
+        AuditQuery, RoleChangeRequest, RoleCreateRequest, RolePermissionsRequest, SanctionRequest,
+        SuspendRequest,
+    },
+};
+
+use super::auth::revoke_user_sessions;
+
+/// Roles created by the initial migration, they can not be deleted
+const BUILT_IN_ROLES: [&str; 3] = ["admin", "moderator", "user"];
+
+/// Keeps every permission, so admins can not lock themselves out
+const ADMIN_ROLE: &str = "admin";
+
+/// Audit events returned per page by default and at most
+const AUDIT_PAGE_SIZE: u64 = 50;
+const AUDIT_PAGE_SIZE_MAX: u64 = 200;
+
+/// Change of a user applied by an admin, recorded in `account_action`
+enum AccountChange {
+    Role(i16),
+    Suspend(NaiveDateTime),
+    Ban,
+    Lift,
+}
+
+impl AccountChange {
+    fn action(&self) -> &'static str {
+        match self {
+            AccountChange::Role(_) => "role",
+            AccountChange::Suspend(_) => "suspend",
+            AccountChange::Ban => "ban",
+            AccountChange::Lift => "lift",
+        }
+    }
+
+    fn event(&self) -> Event {
+        match self {
+            AccountChange::Role(_) => Event::RoleChanged,
+            AccountChange::Suspend(_) => Event::UserSuspended,
+            AccountChange::Ban => Event::UserBanned,
+            AccountChange::Lift => Event::SanctionsLifted,
+        }
+    }
+}
+
+pub async fn rotate_keys(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Rejection> {
+    let db = db_session.lock().await.to_owned();
+
+    match keys::rotate(&db).await {
+        Ok(kid) => {
+            let entry = Entry::success(Event::KeysRotated)
+                .actor(claims.sub as i32)
+                .client(&client)
+                .detail(format!("key {kid}"));
+            audit::record(&db, entry).await;
+
+            Ok(warp::reply::with_status(
+                warp::reply::json(&json!({ "kid": kid })),
+                StatusCode::CREATED,
+            )
+            .into_response())
+        }
+        Err(e) => Err(reject::custom(e)),
+    }
+}
+
+pub async fn list_permissions(
+    _claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    match permission::Entity::find().all(&db).await {
+        Ok(rows) => Ok(warp::reply::json(
+            &rows
+                .into_iter()
+                .map(|row| PermissionInfo {
+                    name: row.name,
+                    description: row.description,
+                })
+                .collect::<Vec<PermissionInfo>>(),
+        )
+        .into_response()),
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+pub async fn list_roles(
+    _claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    match permissions::load(&db).await {
+        Ok(_) => Ok(warp::reply::json(&permissions::all()).into_response()),
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+pub async fn create_role(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: RoleCreateRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let result = async {
+        let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+
+        let taken = role::Entity::find()
+            .filter(role::Column::Name.eq(&body.name))
+            .one(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+        if taken.is_some() {
+            return Err(DbError::AlreadyExists);
+        }
+
+        let created = role::ActiveModel {
+            name: Set(body.name),
+            permissions_version: Set(1),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(|_| DbError::FailedToAdd)?;
+
+        grant(&txn, created.id, &body.permissions).await?;
+        txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+        reload(&db, created.id).await
+    }
+    .await;
+
+    match result {
+        Ok(role) => {
+            record_role(&db, Event::RoleCreated, &claims, &client, &role).await;
+
+            Ok(
+                warp::reply::with_status(warp::reply::json(&role), StatusCode::CREATED)
+                    .into_response(),
+            )
+        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Replaces the permissions of a role, tokens already issued pick them up by the new version
+pub async fn update_role(
+    role_id: i32,
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: RolePermissionsRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let result = async {
+        let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+
+        let existing = role::Entity::find_by_id(role_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?
+            .ok_or(DbError::NotFound)?;
+        if existing.name == ADMIN_ROLE {
+            return Err(DbError::AlreadyExists);
+        }
+
+        role_permission::Entity::delete_many()
+            .filter(role_permission::Column::RoleId.eq(role_id))
+            .exec(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+        grant(&txn, role_id, &body.permissions).await?;
+
+        let version = existing.permissions_version + 1;
+        let mut existing: role::ActiveModel = existing.into();
+        existing.permissions_version = Set(version);
+        existing
+            .update(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+
+        txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+        reload(&db, role_id).await
+    }
+    .await;
+
+    match result {
+        Ok(role) => {
+            record_role(&db, Event::RoleUpdated, &claims, &client, &role).await;
+
+            Ok(warp::reply::json(&role).into_response())
+        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Only custom roles nobody holds can be deleted
+pub async fn delete_role(
+    role_id: i32,
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let result = async {
+        let existing = role::Entity::find_by_id(role_id)
+            .one(&db)
+            .await
+            .map_err(|_| DbError::InternalError)?
+            .ok_or(DbError::NotFound)?;
+        if BUILT_IN_ROLES.contains(&existing.name.as_str()) {
+            return Err(DbError::AlreadyExists);
+        }
+
+        let holders = user::Entity::find()
+            .filter(user::Column::Role.eq(role_id))
+            .count(&db)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+        if holders > 0 {
+            return Err(DbError::AlreadyExists);
+        }
+
+        role::Entity::delete_by_id(role_id)
+            .exec(&db)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+
+        permissions::load(&db)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+
+        Ok(existing.name)
+    }
+    .await;
+
+    match result {
+        Ok(name) => {
+            let entry = Entry::success(Event::RoleDeleted)
+                .actor(claims.sub as i32)
+                .client(&client)
+                .detail(format!("role {role_id} ({name})"));
+            audit::record(&db, entry).await;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn get_user(
+    user_id: i32,
+    _claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    match account_status(&db, user_id).await {
+        Ok(status) => Ok(warp::reply::json(&status).into_response()),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Changes the role, the user logs in again to get the new permissions
+pub async fn change_role(
+    user_id: i32,
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: RoleChangeRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let role = match i16::try_from(body.role_id) {
+        Ok(role) => role,
+        Err(_) => return Ok(error_response(DbError::NotFound)),
+    };
+
+    change_account(
+        db_session,
+        client,
+        claims.sub as i32,
+        user_id,
+        AccountChange::Role(role),
+        body.reason,
+    )
+    .await
+}
+
+pub async fn suspend_user(
+    user_id: i32,
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: SuspendRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    change_account(
+        db_session,
+        client,
+        claims.sub as i32,
+        user_id,
+        AccountChange::Suspend(body.until),
+        body.reason,
+    )
+    .await
+}
+
+pub async fn ban_user(
+    user_id: i32,
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: SanctionRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    change_account(
+        db_session,
+        client,
+        claims.sub as i32,
+        user_id,
+        AccountChange::Ban,
+        body.reason,
+    )
+    .await
+}
+
+/// Lifts suspensions and bans, revoked sessions stay revoked
+pub async fn lift_sanctions(
+    user_id: i32,
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: SanctionRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    change_account(
+        db_session,
+        client,
+        claims.sub as i32,
+        user_id,
+        AccountChange::Lift,
+        body.reason,
+    )
+    .await
+}
+
+/// Applies the change, records it with the reason and revokes the sessions of the user
+async fn change_account(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    client: ClientInfo,
+    admin_id: i32,
+    user_id: i32,
+    change: AccountChange,
+    reason: String,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+    let event = change.event();
+    let detail = match change {
+        AccountChange::Role(role) => format!("role {role}: {reason}"),
+        AccountChange::Suspend(until) => format!("until {until}: {reason}"),
+        _ => reason.clone(),
+    };
+
+    let result = async {
+        // admins can not sanction or demote themselves
+        if admin_id == user_id {
+            return Err(DbError::OwnAccount);
+        }
+
+        if let AccountChange::Role(role) = change {
+            permissions::role(&db, role.into()).await?;
+        }
+
+        let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+
+        let target = user::Entity::find_by_id(user_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?
+            .ok_or(DbError::NotFound)?;
+
+        let mut record = account_action::ActiveModel {
+            user_id: Set(user_id),
+            admin_id: Set(admin_id),
+            action: Set(change.action().to_string()),
+            reason: Set(reason),
+            ..Default::default()
+        };
+
+        let mut target: user::ActiveModel = target.into();
+        match change {
+            AccountChange::Role(role) => {
+                target.role = Set(role);
+                record.role = Set(Some(role));
+            }
+            AccountChange::Suspend(until) => {
+                target.suspended_until = Set(Some(until));
+                record.until = Set(Some(until));
+            }
+            AccountChange::Ban => target.banned_at = Set(Some(Utc::now().naive_utc())),
+            AccountChange::Lift => {
+                target.suspended_until = Set(None);
+                target.banned_at = Set(None);
+            }
+        }
+
+        target
+            .update(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+        record
+            .insert(&txn)
+            .await
+            .map_err(|_| DbError::FailedToAdd)?;
+
+        if !matches!(change, AccountChange::Lift) {
+            revoke_user_sessions(&txn, user_id).await?;
+        }
+
+        txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+        log::info!(
+            "Admin {admin_id} applied {} to user {user_id}",
+            change.action()
+        );
+
+        account_status(&db, user_id).await
+    }
+    .await;
+
+    let entry = match result {
+        Ok(_) => Entry::success(event),
+        Err(_) => Entry::failure(event),
+    };
+    let entry = entry
+        .actor(admin_id)
+        .target(user_id)
+        .client(&client)
+        .detail(detail);
+    audit::record(&db, entry).await;
+
+    match result {
+        Ok(status) => Ok(warp::reply::json(&status).into_response()),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Searches the audit log, newest first
+///
+/// `user_id` matches events the user performed or was affected by,
+/// older pages are fetched by passing the last seen id as `before`
+pub async fn list_audit_events(
+    _claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    query: AuditQuery,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let mut select = audit_event::Entity::find();
+    if let Some(user_id) = query.user_id {
+        select = select.filter(
+            audit_event::Column::ActorId
+                .eq(user_id)
+                .or(audit_event::Column::TargetId.eq(user_id)),
+        );
+    }
+    if let Some(event) = query.event {
+        select = select.filter(audit_event::Column::Event.eq(event));
+    }
+    if let Some(from) = query.from {
+        select = select.filter(audit_event::Column::CreatedAt.gte(from));
+    }
+    if let Some(to) = query.to {
+        select = select.filter(audit_event::Column::CreatedAt.lt(to));
+    }
+    if let Some(before) = query.before {
+        select = select.filter(audit_event::Column::Id.lt(before));
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(AUDIT_PAGE_SIZE)
+        .clamp(1, AUDIT_PAGE_SIZE_MAX);
+
+    let events = select
+        .order_by_desc(audit_event::Column::Id)
+        .limit(limit)
+        .all(&db)
+        .await;
+
+    match events {
+        Ok(events) => Ok(warp::reply::json(
+            &events
+                .into_iter()
+                .map(AuditEventInfo::from)
+                .collect::<Vec<AuditEventInfo>>(),
+        )
+        .into_response()),
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+async fn account_status(db: &DatabaseConnection, user_id: i32) -> Result<AccountStatus, DbError> {
+    let (target, actions) = user::Entity::find_by_id(user_id)
+        .find_with_related(account_action::Entity)
+        .order_by_desc(account_action::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .into_iter()
+        .next()
+        .ok_or(DbError::NotFound)?;
+
+    Ok(AccountStatus {
+        id: target.id,
+        username: target.username,
+        role: target.role,
+        suspended_until: target.suspended_until,
+        banned_at: target.banned_at,
+        actions: actions
+            .into_iter()
+            .map(|action| AccountActionInfo {
+                id: action.id,
+                admin_id: action.admin_id,
+                action: action.action,
+                reason: action.reason,
+                role: action.role,
+                until: action.until,
+                created_at: action.created_at,
+            })
+            .collect(),
+    })
+}
+
+async fn record_role(
+    db: &DatabaseConnection,
+    event: Event,
+    claims: &Claims,
+    client: &ClientInfo,
+    role: &RoleInfo,
+) {
+    let entry = Entry::success(event)
+        .actor(claims.sub as i32)
+        .client(client)
+        .detail(format!(
+            "role {} ({}): {}",
+            role.id,
+            role.name,
+            Permission::join(&role.permissions)
+        ));
+    audit::record(db, entry).await;
+}
+
+async fn grant(
+    txn: &DatabaseTransaction,
+    role_id: i32,
+    permissions: &[Permission],
+) -> Result<(), DbError> {
+    if permissions.is_empty() {
+        return Ok(());
+    }
+
+    let mut granted = permissions.to_vec();
+    granted.sort_by_key(|permission| permission.to_string());
+    granted.dedup();
+
+    role_permission::Entity::insert_many(granted.into_iter().map(|permission| {
+        role_permission::ActiveModel {
+            role_id: Set(role_id),
+            permission: Set(permission.to_string()),
+        }
+    }))
+    .exec(txn)
+    .await
+    .map(|_| ())
+    .map_err(|_| DbError::FailedToAdd)
+}
+
+async fn reload(db: &DatabaseConnection, role_id: i32) -> Result<RoleInfo, DbError> {
+    permissions::load(db)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+    permissions::role(db, role_id).await
+}
+
+fn error_response(e: DbError) -> warp::reply::Response {
+    let code = match e {
+        DbError::NotFound => StatusCode::NOT_FOUND,
+        // taken names, built-in roles and roles still held
+        DbError::AlreadyExists => StatusCode::CONFLICT,
+        DbError::OwnAccount => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    warp::reply::with_status(warp::reply::json(&e), code).into_response()
+}
@@ -1,33 +1,128 @@
 use std::{convert::Infallible, sync::Arc};
 
+use chrono::{NaiveDateTime, Utc};
 use sea_orm::Set;
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
 use tokio::sync::Mutex;
-use warp::{hyper::StatusCode, reject, Reply};
+use warp::{
+    http::HeaderValue,
+    hyper::{HeaderMap, StatusCode},
+    reject, Rejection, Reply,
+};
 
+use entity::refresh_token;
+use entity::refresh_token::Entity as RefreshToken;
 use entity::session;
 use entity::session::Entity as Session;
 use entity::user;
 use entity::user::Entity as User;
 
-use crate::models::role::Role;
+use crate::audit::{self, Entry, Event};
+use crate::cookies;
+use crate::models::session::{AuthTokens, ClientInfo, Login, SessionInfo};
+use crate::password::{self, Verification};
+use crate::permissions;
+use crate::throttle;
+use crate::tokens;
 use crate::{
     errors::{db::DbError, jwt::JWTError},
     filters::auth::check_token,
-    jwt::generate_jwt,
-    requests::auth::{AuthRequest, LogoutRequest},
+    jwt::{generate_jwt, Claims, REFRESH_TOKEN_LIFETIME, TOKEN_LIFETIME},
+    requests::auth::{AuthRequest, LogoutRequest, RefreshRequest},
+};
+
+use super::{
+    two_factor,
+    users::{create, normalize_identifier},
+    verification::send_verification,
 };
 
-use super::users::create;
+/// How precisely the last use of a session is tracked, in seconds
+const LAST_USED_PRECISION: i64 = 60;
 
+/// Logs in by username or email
+///
+/// Failed attempts lock the account out for an exponentially growing time
 pub async fn login(
     session: Arc<Mutex<DatabaseConnection>>,
+    client: ClientInfo,
     body: AuthRequest,
-) -> Result<warp::reply::Response, Infallible> {
-    let result = validate_user(session, body).await;
+) -> Result<warp::reply::Response, Rejection> {
+    let username = login_identifier(&body);
+
+    let user = {
+        let db = session.lock().await.to_owned();
+        find_login_user(&db, &username).await
+    };
+    let user = match user {
+        Ok(user) => user,
+        Err(e) => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+            .into_response())
+        }
+    };
+
+    let user_id = user.as_ref().map(|user| user.id);
+    let account = throttle::account_key(user_id, &username);
+    throttle::count_attempt(&account).await?;
+
+    let cookie = body.cookie && cookies::enabled();
+    let result = validate_user(session.clone(), user, body, client.clone()).await;
+
+    // the attempt counts as failed unless the password was right,
+    // the failures are only cleared once the second factor passed as well
+    match &result {
+        Err(DbError::WrongCredentials | DbError::InternalError)
+        | Ok(Login::TwoFactorPending(_)) => (),
+        _ => throttle::clear_failures(&account).await,
+    }
+
+    // successful logins are recorded once the session exists
+    if let Err(
+        e @ (DbError::WrongCredentials | DbError::EmailNotVerified | DbError::AccountSanctioned),
+    ) = &result
+    {
+        let db = session.lock().await.to_owned();
+        let mut entry = Entry::failure(Event::Login)
+            .client(&client)
+            .detail(format!("{username}: {e:?}"));
+        // attempts against existing accounts show up when filtering by user
+        if let Some(user_id) = user_id {
+            entry = entry.target(user_id);
+        }
+        audit::record(&db, entry).await;
+    }
 
     match result {
-        Ok(token) => Ok(warp::reply::json(&token).into_response()),
+        Ok(Login::Authenticated(tokens)) if cookie => Ok(cookies::session_reply(tokens)),
+        Ok(Login::Authenticated(tokens)) => Ok(warp::reply::json(&tokens).into_response()),
+        // the login is finished on POST /auth/2fa/login
+        Ok(Login::TwoFactorPending(challenge)) => Ok(warp::reply::with_status(
+            warp::reply::json(&challenge),
+            StatusCode::ACCEPTED,
+        )
+        .into_response()),
+        Err(DbError::EmailNotVerified) => Ok(warp::reply::with_status(
+            warp::reply::json(&DbError::EmailNotVerified),
+            StatusCode::FORBIDDEN,
+        )
+        .into_response()),
+        Err(DbError::WrongCredentials) => Ok(warp::reply::with_status(
+            warp::reply::json(&DbError::WrongCredentials),
+            StatusCode::UNAUTHORIZED,
+        )
+        .into_response()),
+        Err(DbError::AccountSanctioned) => Ok(warp::reply::with_status(
+            warp::reply::json(&DbError::AccountSanctioned),
+            StatusCode::FORBIDDEN,
+        )
+        .into_response()),
         Err(e) => Ok(warp::reply::with_status(
             warp::reply::json(&e),
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -36,28 +131,48 @@ pub async fn login(
     }
 }
 
+/// Creates an unverified account and mails the verification link,
+/// the user can log in once the address is verified
 pub async fn register(
     db_session: Arc<Mutex<DatabaseConnection>>,
     body: AuthRequest,
 ) -> Result<warp::reply::Response, Infallible> {
+    let password_hash = match password::hash(body.password.clone()).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            log::error!("Failed to hash password: {e}");
+            return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    let username = normalize_identifier(&body.username);
+
     let user = user::ActiveModel {
-        username: Set(body.username.clone()),
-        email: Set(body.email.clone()),
-        password: Set(body.password.clone()),
+        username: Set(username.clone()),
+        email: Set(normalize_identifier(&body.email)),
+        password: Set(password_hash),
         ..Default::default()
     };
 
     let created = create(user, db_session.clone()).await.unwrap();
 
-    if !created.is_success() {
-        return Ok(created.into_response());
+    if !created.status().is_success() {
+        return Ok(created);
     }
 
-    let result = validate_user(db_session, body).await;
+    let db = db_session.lock().await.to_owned();
+    let user = User::find()
+        .filter(user::Column::Username.eq(&username))
+        .one(&db)
+        .await;
 
-    log::error!("{result:?}");
-    match result {
-        Ok(token) => Ok(warp::reply::json(&token).into_response()),
+    let user = match user {
+        Ok(Some(user)) => user,
+        _ => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    match send_verification(&db, &user).await {
+        Ok(_) => Ok(StatusCode::CREATED.into_response()),
         Err(e) => Ok(warp::reply::with_status(
             warp::reply::json(&e),
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -66,90 +181,667 @@ pub async fn register(
     }
 }
 
+/// Ends the session of the token in the body, or of the session cookie
 pub async fn logout(
     db_session: Arc<Mutex<DatabaseConnection>>,
+    client: ClientInfo,
+    headers: HeaderMap<HeaderValue>,
     body: LogoutRequest,
-) -> Result<warp::reply::Response, Infallible> {
-    let _token_decoded = check_token(db_session.clone(), body.token.clone())
+) -> Result<warp::reply::Response, Rejection> {
+    let cookie = body.token.is_none();
+    let token = match body.token {
+        Some(token) => Some(token),
+        None => session_cookie(&headers, cookies::ACCESS_COOKIE)?,
+    };
+
+    let mut owner = match token {
+        Some(token) => check_token(db_session.clone(), token)
+            .await
+            .ok()
+            .map(|decoded| (decoded.claims.sid, decoded.claims.sub as i32)),
+        None => None,
+    };
+
+    // the access cookie outlives its token, the refresh token still identifies the session
+    if owner.is_none() && cookie {
+        if let Some(token) = session_cookie(&headers, cookies::REFRESH_COOKIE)? {
+            owner = match refresh_token_owner(db_session.clone(), &token).await {
+                Ok(owner) => owner,
+                Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+            };
+        }
+    }
+
+    let code = match owner {
+        Some((session_id, user_id)) => end_session(db_session, session_id, user_id, client).await,
+        None => StatusCode::UNAUTHORIZED,
+    };
+
+    // the browser drops the cookies whether the session was still alive or not
+    let mut response = code.into_response();
+    if cookies::enabled() {
+        cookies::clear(&mut response);
+    }
+
+    Ok(response)
+}
+
+/// Session and user a refresh token was issued to, if the session is still active
+async fn refresh_token_owner(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    token: &str,
+) -> Result<Option<(i32, i32)>, DbError> {
+    let db = db_session.lock().await.to_owned();
+
+    let presented = RefreshToken::find_by_id(tokens::hash(token))
+        .one(&db)
         .await
-        .map_err(|_| reject::custom(JWTError::JWTTokenError))
-        .unwrap();
+        .map_err(|_| DbError::InternalError)?;
+    let presented = match presented {
+        Some(presented) => presented,
+        None => return Ok(None),
+    };
 
+    let user_session = Session::find_by_id(presented.session_id)
+        .filter(session::Column::RevokedAt.is_null())
+        .filter(session::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+        .one(&db)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    Ok(user_session.map(|user_session| (user_session.id, user_session.user_id)))
+}
+
+async fn end_session(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    session_id: i32,
+    user_id: i32,
+    client: ClientInfo,
+) -> StatusCode {
+    let result = revoke_session(db_session.clone(), session_id).await;
+
+    if result.is_ok() {
+        let db = db_session.lock().await.to_owned();
+        let entry = Entry::success(Event::Logout)
+            .actor(user_id)
+            .target(user_id)
+            .client(&client);
+        audit::record(&db, entry).await;
+    }
+
+    match result {
+        Ok(_) => StatusCode::UNAUTHORIZED,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Exchanges the refresh token in the body, or of the session cookie,
+/// cookie sessions get their new tokens as cookies again
+pub async fn refresh(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    client: ClientInfo,
+    headers: HeaderMap<HeaderValue>,
+    body: RefreshRequest,
+) -> Result<warp::reply::Response, Rejection> {
+    let (token, cookie) = match body.refresh_token {
+        Some(token) => (Some(token), false),
+        None => (session_cookie(&headers, cookies::REFRESH_COOKIE)?, true),
+    };
+
+    let token = match token {
+        Some(token) => token,
+        None => {
+            return Ok(warp::reply::with_status(
+                warp::reply::json(&DbError::WrongCredentials),
+                StatusCode::UNAUTHORIZED,
+            )
+            .into_response())
+        }
+    };
+
+    let result = rotate_refresh_token(db_session, &token, client).await;
+
+    match result {
+        Ok(tokens) if cookie => Ok(cookies::session_reply(tokens)),
+        Ok(tokens) => Ok(warp::reply::json(&tokens).into_response()),
+        Err(DbError::WrongCredentials) => Ok(warp::reply::with_status(
+            warp::reply::json(&DbError::WrongCredentials),
+            StatusCode::UNAUTHORIZED,
+        )
+        .into_response()),
+        Err(DbError::AccountSanctioned) => Ok(warp::reply::with_status(
+            warp::reply::json(&DbError::AccountSanctioned),
+            StatusCode::FORBIDDEN,
+        )
+        .into_response()),
+        Err(e) => Ok(warp::reply::with_status(
+            warp::reply::json(&e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .into_response()),
+    }
+}
+
+/// Reads a token from a session cookie, the request has to carry the CSRF token
+fn session_cookie(
+    headers: &HeaderMap<HeaderValue>,
+    name: &str,
+) -> Result<Option<String>, Rejection> {
+    match cookies::read(headers, name) {
+        Some(_) if !cookies::csrf_valid(headers) => Err(reject::custom(JWTError::CsrfTokenError)),
+        token => Ok(token),
+    }
+}
+
+pub async fn list_sessions(
+    claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let sessions = Session::find()
+        .filter(session::Column::UserId.eq(claims.sub as i32))
+        .filter(session::Column::RevokedAt.is_null())
+        .filter(session::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+        .order_by_desc(session::Column::LastUsedAt)
+        .all(&db)
+        .await;
+
+    if sessions.is_err() {
+        return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+    }
+
+    let sessions = sessions
+        .unwrap()
+        .into_iter()
+        .map(|user_session| SessionInfo {
+            current: user_session.id == claims.sid,
+            id: user_session.id,
+            user_agent: user_session.user_agent,
+            ip: user_session.ip,
+            created_at: user_session.created_at,
+            last_used_at: user_session.last_used_at,
+        })
+        .collect::<Vec<SessionInfo>>();
+
+    Ok(warp::reply::json(&sessions).into_response())
+}
+
+pub async fn delete_session(
+    session_id: i32,
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
     let db = db_session.lock().await.to_owned();
-    let result = session::Entity::delete_by_id(body.token).exec(&db).await;
+
+    // sessions of other users are reported as missing
+    let result = Session::update_many()
+        .col_expr(session::Column::RevokedAt, Utc::now().naive_utc().into())
+        .filter(session::Column::Id.eq(session_id))
+        .filter(session::Column::UserId.eq(claims.sub as i32))
+        .filter(session::Column::RevokedAt.is_null())
+        .exec(&db)
+        .await;
 
     match result {
-        Ok(_) => Ok(StatusCode::UNAUTHORIZED.into_response()),
+        Ok(res) if res.rows_affected == 0 => Ok(StatusCode::NOT_FOUND.into_response()),
+        Ok(_) => {
+            let entry = Entry::success(Event::SessionRevoked)
+                .actor(claims.sub as i32)
+                .target(claims.sub as i32)
+                .client(&client)
+                .detail(format!("session {session_id}"));
+            audit::record(&db, entry).await;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+pub async fn delete_other_sessions(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let result = Session::update_many()
+        .col_expr(session::Column::RevokedAt, Utc::now().naive_utc().into())
+        .filter(session::Column::UserId.eq(claims.sub as i32))
+        .filter(session::Column::Id.ne(claims.sid))
+        .filter(session::Column::RevokedAt.is_null())
+        .exec(&db)
+        .await;
+
+    match result {
+        Ok(res) => {
+            let entry = Entry::success(Event::SessionRevoked)
+                .actor(claims.sub as i32)
+                .target(claims.sub as i32)
+                .client(&client)
+                .detail(format!("{} other sessions", res.rows_affected));
+            audit::record(&db, entry).await;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
         Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
     }
 }
 
+/// Account a login identifier belongs to, by username or email
+async fn find_login_user(
+    db: &DatabaseConnection,
+    identifier: &str,
+) -> Result<Option<user::Model>, DbError> {
+    let mut users: Vec<user::Model> = User::find()
+        .filter(
+            Condition::any()
+                .add(user::Column::Username.eq(identifier))
+                .add(user::Column::Email.eq(identifier)),
+        )
+        .all(db)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    // a username matching the address of another account wins
+    users.sort_by_key(|user| user.username != identifier);
+    Ok(users.into_iter().next())
+}
+
+/// Checks the password against the account found by `find_login_user`
 pub async fn validate_user(
     db_session: Arc<Mutex<DatabaseConnection>>,
+    user: Option<user::Model>,
     body: AuthRequest,
-) -> Result<String, DbError> {
+    client: ClientInfo,
+) -> Result<Login, DbError> {
     let db = db_session.lock().await.to_owned();
 
-    let user: Option<user::Model> = User::find()
-        .filter(user::Column::Username.contains(&body.username))
-        .one(&db)
+    // anonymized accounts keep their row, but can not log in anymore,
+    // both cases take as long as a wrong password
+    let user = match user {
+        Some(user) if user.deleted_at.is_none() => user,
+        _ => {
+            password::verify_dummy(body.password).await;
+            return Err(DbError::WrongCredentials);
+        }
+    };
+
+    let verification = password::verify(body.password.clone(), user.password.clone())
         .await
-        .unwrap();
+        .map_err(|e| {
+            log::error!("Failed to verify password: {e}");
+            DbError::InternalError
+        })?;
 
-    if user.is_none() {
-        return Err(DbError::WrongCredentials);
+    match verification {
+        Verification::Invalid => return Err(DbError::WrongCredentials),
+        Verification::Valid => (),
+        // upgrading legacy plaintext rows and outdated hashes transparently
+        Verification::NeedsRehash => {
+            if let Err(e) = rehash_password(&db, &user, body.password).await {
+                log::error!("Failed to upgrade password hash of user {}: {e:?}", user.id);
+            }
+        }
     }
 
-    let user = user.unwrap();
+    if user.email_verified_at.is_none() {
+        return Err(DbError::EmailNotVerified);
+    }
 
-    if user.password != body.password {
-        return Err(DbError::WrongCredentials);
+    if is_sanctioned(&user, Utc::now().naive_utc()) {
+        return Err(DbError::AccountSanctioned);
+    }
+
+    if two_factor::is_enabled(&db, user.id).await? {
+        return two_factor::create_challenge(&db, user.id)
+            .await
+            .map(Login::TwoFactorPending);
+    }
+
+    add_jwt_session(db_session, &user, client)
+        .await
+        .map(Login::Authenticated)
+}
+
+/// The username, or the email when no username was sent, normalized like stored ones
+fn login_identifier(body: &AuthRequest) -> String {
+    match body.username.trim().is_empty() {
+        true => normalize_identifier(&body.email),
+        false => normalize_identifier(&body.username),
     }
+}
+
+async fn rehash_password(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    password: String,
+) -> Result<(), DbError> {
+    let password_hash = password::hash(password)
+        .await
+        .map_err(|_| DbError::InternalError)?;
 
-    add_jwt_session(db_session, &user).await
+    let mut user: user::ActiveModel = user.to_owned().into();
+    user.password = Set(password_hash);
+
+    match user.update(db).await {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::InternalError),
+    }
 }
 
+/// Starts a new session family and hands out its first tokens
 pub async fn add_jwt_session(
     db_session: Arc<Mutex<DatabaseConnection>>,
     user: &user::Model,
-) -> Result<String, DbError> {
-    let user = user.to_owned();
-
-    let token = generate_jwt(
-        user.id,
-        Role::from_u8(user.role.clone() as u8).unwrap(),
-    );
-    let token = token.unwrap();
+    client: ClientInfo,
+) -> Result<AuthTokens, DbError> {
+    // also covers logins finished through 2FA
+    if is_sanctioned(user, Utc::now().naive_utc()) {
+        return Err(DbError::AccountSanctioned);
+    }
 
     let db = db_session.lock().await.to_owned();
+    let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+
     let user_session = session::ActiveModel {
-        jwt: Set(token.clone()),
         user_id: Set(user.id),
+        user_agent: Set(client.user_agent.clone()),
+        ip: Set(client.ip.clone()),
+        expires_at: Set(Utc::now().naive_utc() + chrono::Duration::seconds(REFRESH_TOKEN_LIFETIME)),
         ..Default::default()
-    };
-    let res = session::Entity::insert(user_session).exec(&db).await;
+    }
+    .insert(&txn)
+    .await
+    .map_err(|_| DbError::FailedToAdd)?;
+
+    let refresh_token = add_refresh_token(&txn, user_session.id).await?;
+
+    txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+    let entry = Entry::success(Event::Login)
+        .actor(user.id)
+        .target(user.id)
+        .client(&client)
+        .detail(format!("session {}", user_session.id));
+    audit::record(&db, entry).await;
+
+    issue_tokens(&db, user, user_session.id, refresh_token).await
+}
+
+/// Exchanges a refresh token for new tokens, the presented one is used up
+///
+/// Presenting an already used token means it was stolen,
+/// so the whole session family gets revoked
+pub async fn rotate_refresh_token(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    token: &str,
+    client: ClientInfo,
+) -> Result<AuthTokens, DbError> {
+    let db = db_session.lock().await.to_owned();
+    let now = Utc::now().naive_utc();
+
+    let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+
+    let presented = RefreshToken::find_by_id(tokens::hash(token))
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::WrongCredentials)?;
 
-    if res.is_err() {
-        return Err(DbError::FailedToAdd);
+    let user_session = Session::find_by_id(presented.session_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::WrongCredentials)?;
+
+    if user_session.revoked_at.is_some() || user_session.expires_at < now {
+        return Err(DbError::WrongCredentials);
+    }
+
+    if presented.used_at.is_some() {
+        log::warn!(
+            "Refresh token reuse detected, revoking session {}",
+            user_session.id
+        );
+
+        let user_id = user_session.user_id;
+        let detail = format!("session {}", user_session.id);
+
+        let mut user_session: session::ActiveModel = user_session.into();
+        user_session.revoked_at = Set(Some(now));
+        user_session
+            .update(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+        txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+        let entry = Entry::failure(Event::RefreshTokenReused)
+            .target(user_id)
+            .client(&client)
+            .detail(detail);
+        audit::record(&db, entry).await;
+
+        return Err(DbError::WrongCredentials);
+    }
+
+    if presented.expires_at < now {
+        return Err(DbError::WrongCredentials);
     }
 
+    let mut presented: refresh_token::ActiveModel = presented.into();
+    presented.used_at = Set(Some(now));
+    presented
+        .update(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    let refresh_token = add_refresh_token(&txn, user_session.id).await?;
+
+    let mut user_session: session::ActiveModel = user_session.into();
+    user_session.user_agent = Set(client.user_agent);
+    user_session.ip = Set(client.ip);
+    user_session.last_used_at = Set(now);
+    user_session.expires_at = Set(now + chrono::Duration::seconds(REFRESH_TOKEN_LIFETIME));
+    let user_session = user_session
+        .update(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    let user = User::find_by_id(user_session.user_id)
+        .one(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::NotFound)?;
+
+    if is_sanctioned(&user, now) {
+        return Err(DbError::AccountSanctioned);
+    }
+
+    txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+    issue_tokens(&db, &user, user_session.id, refresh_token).await
+}
+
+async fn add_refresh_token(txn: &DatabaseTransaction, session_id: i32) -> Result<String, DbError> {
+    let token = tokens::generate();
+    // sessions expire together with their latest refresh token
+    let expires_at = Utc::now().naive_utc() + chrono::Duration::seconds(REFRESH_TOKEN_LIFETIME);
+
+    refresh_token::ActiveModel {
+        token_hash: Set(tokens::hash(&token)),
+        session_id: Set(session_id),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await
+    .map_err(|_| DbError::FailedToAdd)?;
+
     Ok(token)
 }
 
+async fn issue_tokens(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    session_id: i32,
+    refresh_token: String,
+) -> Result<AuthTokens, DbError> {
+    let role = permissions::role(db, user.role.into())
+        .await
+        .map_err(|_| DbError::FailedToConvertRow)?;
+    let access_token =
+        generate_jwt(user.id, session_id, &role).map_err(|_| DbError::InternalError)?;
+
+    Ok(AuthTokens {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_LIFETIME,
+    })
+}
+
+/// Checks that the session of an access token has not been revoked
 pub async fn validate_session(
     db_session: Arc<Mutex<DatabaseConnection>>,
-    token: &str,
+    session_id: i32,
 ) -> Result<(), DbError> {
     let db = db_session.lock().await.to_owned();
-    let result = Session::find_by_id(token).one(&db).await;
+    let result = Session::find_by_id(session_id)
+        .find_also_related(User)
+        .one(&db)
+        .await;
 
     if result.is_err() {
         return Err(DbError::InternalError);
     }
 
-    if result.unwrap().is_none() {
-        return Err(DbError::NotFound);
+    let now = Utc::now().naive_utc();
+    let user_session = match result.unwrap() {
+        Some((user_session, Some(user)))
+            if user_session.revoked_at.is_none() && user_session.expires_at > now =>
+        {
+            if is_sanctioned(&user, now) {
+                return Err(DbError::AccountSanctioned);
+            }
+            user_session
+        }
+        _ => return Err(DbError::NotFound),
+    };
+
+    // keeping writes down to one per minute and session
+    if user_session.last_used_at + chrono::Duration::seconds(LAST_USED_PRECISION) < now {
+        let mut user_session: session::ActiveModel = user_session.into();
+        user_session.last_used_at = Set(now);
+        if user_session.update(&db).await.is_err() {
+            log::error!("Failed to update the last use of session {session_id}");
+        }
     }
 
     Ok(())
 }
+
+/// Revokes the session, its access and refresh tokens stop working
+pub async fn revoke_session(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    session_id: i32,
+) -> Result<(), DbError> {
+    let db = db_session.lock().await.to_owned();
+
+    let result = Session::update_many()
+        .col_expr(session::Column::RevokedAt, Utc::now().naive_utc().into())
+        .filter(session::Column::Id.eq(session_id))
+        .filter(session::Column::RevokedAt.is_null())
+        .exec(&db)
+        .await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(_) => Err(DbError::InternalError),
+    }
+}
+
+/// Revokes every active session of the user
+pub async fn revoke_user_sessions<C: ConnectionTrait>(db: &C, user_id: i32) -> Result<(), DbError> {
+    Session::update_many()
+        .col_expr(session::Column::RevokedAt, Utc::now().naive_utc().into())
+        .filter(session::Column::UserId.eq(user_id))
+        .filter(session::Column::RevokedAt.is_null())
+        .exec(db)
+        .await
+        .map(|_| ())
+        .map_err(|_| DbError::InternalError)
+}
+
+/// Whether the user is banned or suspended at the moment
+pub fn is_sanctioned(user: &user::Model, now: NaiveDateTime) -> bool {
+    user.banned_at.is_some() || user.suspended_until.is_some_and(|until| until > now)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    fn user() -> user::Model {
+        user::Model {
+            id: 1,
+            username: "bob".to_owned(),
+            email: "bob@example.com".to_owned(),
+            password: String::new(),
+            followers: 0,
+            following: 0,
+            role: 0,
+            created_at: now(),
+            email_verified_at: Some(now()),
+            suspended_until: None,
+            banned_at: None,
+            deleted_at: None,
+            display_name: None,
+            bio: None,
+            location: None,
+            website: None,
+            avatar_url: None,
+            banner_url: None,
+        }
+    }
+
+    #[test]
+    fn unsanctioned_user() {
+        assert!(!is_sanctioned(&user(), now()));
+    }
+
+    #[test]
+    fn banned_user() {
+        let user = user::Model {
+            banned_at: Some(now() - Duration::days(1)),
+            ..user()
+        };
+
+        assert!(is_sanctioned(&user, now()));
+    }
+
+    #[test]
+    fn suspension_ends() {
+        let user = user::Model {
+            suspended_until: Some(now() + Duration::hours(1)),
+            ..user()
+        };
+
+        assert!(is_sanctioned(&user, now()));
+        assert!(!is_sanctioned(&user, now() + Duration::hours(1)));
+        assert!(!is_sanctioned(&user, now() + Duration::days(1)));
+    }
+}
@@ -0,0 +1,139 @@
+use std::{convert::Infallible, sync::Arc};
+
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use tokio::sync::Mutex;
+use tokio_util::io::ReaderStream;
+use warp::{
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        HeaderValue,
+    },
+    hyper::{Body, StatusCode},
+    Reply,
+};
+
+use entity::data_export;
+
+use crate::{
+    exports::{self, STATUS_PENDING, STATUS_READY, STATUS_RUNNING},
+    jwt::Claims,
+    models::export::ExportInfo,
+};
+
+/// Starts an export of the own data, a running one is returned instead of starting another
+pub async fn request(
+    claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+    let user_id = claims.sub as i32;
+
+    let running = data_export::Entity::find()
+        .filter(data_export::Column::UserId.eq(user_id))
+        .filter(data_export::Column::Status.is_in([STATUS_PENDING, STATUS_RUNNING]))
+        .one(&db)
+        .await;
+
+    let export = match running {
+        Ok(Some(export)) => export,
+        Ok(None) => {
+            let created = data_export::ActiveModel {
+                user_id: Set(user_id),
+                status: Set(STATUS_PENDING.to_owned()),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await;
+
+            match created {
+                Ok(export) => {
+                    exports::start(db.clone(), export.id, user_id);
+                    export
+                }
+                Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+            }
+        }
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ExportInfo::from(export)),
+        StatusCode::ACCEPTED,
+    )
+    .into_response())
+}
+
+pub async fn status(
+    export_id: i32,
+    claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    match find_own(&db, export_id, claims.sub as i32).await {
+        Ok(Some(export)) => Ok(warp::reply::json(&ExportInfo::from(export)).into_response()),
+        Ok(None) => Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+/// Streams the archive of a finished export
+pub async fn download(
+    export_id: i32,
+    claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let export = match find_own(&db, export_id, claims.sub as i32).await {
+        Ok(Some(export)) => export,
+        Ok(None) => return Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    if export.status != STATUS_READY {
+        return Ok(StatusCode::CONFLICT.into_response());
+    }
+
+    let path = match export.file_path {
+        Some(path)
+            if export
+                .expires_at
+                .is_none_or(|at| at > Utc::now().naive_utc()) =>
+        {
+            path
+        }
+        _ => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+
+    let file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(e) => {
+            log::error!("Failed to open export archive {path}: {e}");
+            return Ok(StatusCode::NOT_FOUND.into_response());
+        }
+    };
+
+    let mut response = warp::reply::Response::new(Body::wrap_stream(ReaderStream::new(file)));
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/zip"));
+    if let Ok(disposition) = HeaderValue::from_str(&format!(
+        "attachment; filename=\"nova-export-{export_id}.zip\""
+    )) {
+        headers.insert(CONTENT_DISPOSITION, disposition);
+    }
+
+    Ok(response)
+}
+
+async fn find_own(
+    db: &DatabaseConnection,
+    export_id: i32,
+    user_id: i32,
+) -> Result<Option<data_export::Model>, sea_orm::DbErr> {
+    data_export::Entity::find_by_id(export_id)
+        .filter(data_export::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+}
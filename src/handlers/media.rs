@@ -0,0 +1,189 @@
+use std::{convert::Infallible, sync::Arc};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::TryStreamExt;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use tokio::sync::Mutex;
+use warp::{
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE},
+        HeaderValue,
+    },
+    hyper::{Body, StatusCode},
+    multipart::{FormData, Part},
+    reject, Rejection, Reply,
+};
+
+use entity::media;
+
+use crate::{
+    errors::media::MediaError,
+    media::{self as uploads, Processed},
+    models::media::MediaInfo,
+    requests::media::MediaUploadRequest,
+    storage::storage,
+    validation,
+};
+
+/// Stores an image of the user, which can then be attached to a post
+pub async fn upload(
+    id_from_token: i32,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    form: FormData,
+) -> Result<warp::reply::Response, Rejection> {
+    let req = read_form(form).await?;
+    validation::check(&req).map_err(reject::custom)?;
+
+    let file = req.file.unwrap_or_default();
+    let max_size = uploads::max_size();
+    if file.len() > max_size {
+        return Err(reject::custom(MediaError::TooLarge(max_size)));
+    }
+
+    let Processed {
+        content_type,
+        extension,
+        original,
+        thumbnail,
+        width,
+        height,
+    } = uploads::process(file).await.map_err(reject::custom)?;
+
+    let (original_key, thumbnail_key) = uploads::keys(id_from_token, extension);
+    let size = original.len();
+
+    let stored = async {
+        storage()
+            .put(&original_key, content_type, Bytes::from(original))
+            .await?;
+        storage()
+            .put(&thumbnail_key, content_type, Bytes::from(thumbnail))
+            .await
+    }
+    .await;
+
+    if let Err(e) = stored {
+        log::error!("Failed to store media of user {id_from_token}: {e}");
+        uploads::remove_keys(&[&original_key, &thumbnail_key]).await;
+        return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+    }
+
+    let db = db_session.lock().await.to_owned();
+    let upload = media::ActiveModel {
+        user_id: Set(id_from_token),
+        original_key: Set(original_key.clone()),
+        thumbnail_key: Set(thumbnail_key.clone()),
+        content_type: Set(content_type.to_string()),
+        width: Set(width as i32),
+        height: Set(height as i32),
+        size: Set(size as i32),
+        alt_text: Set(req
+            .alt_text
+            .map(|alt_text| alt_text.trim().to_string())
+            .filter(|alt_text| !alt_text.is_empty())),
+        ..Default::default()
+    };
+
+    match upload.insert(&db).await {
+        Ok(upload) => Ok(warp::reply::with_status(
+            warp::reply::json(&MediaInfo::from(upload)),
+            StatusCode::CREATED,
+        )
+        .into_response()),
+        Err(_) => {
+            uploads::remove_keys(&[&original_key, &thumbnail_key]).await;
+            Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+/// Serves the stored image
+pub async fn get(
+    id: i32,
+    id_from_token: i32,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    serve(id, id_from_token, db_session, |upload| upload.original_key).await
+}
+
+/// Serves the thumbnail of the image
+pub async fn get_thumbnail(
+    id: i32,
+    id_from_token: i32,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    serve(id, id_from_token, db_session, |upload| upload.thumbnail_key).await
+}
+
+async fn serve(
+    id: i32,
+    id_from_token: i32,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    key: fn(media::Model) -> String,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    // uploads not attached yet are only visible to their owner
+    let upload = match media::Entity::find_by_id(id).one(&db).await {
+        Ok(Some(upload)) if upload.post_id.is_some() || upload.user_id == id_from_token => upload,
+        Ok(_) => return Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    let content_type = upload.content_type.clone();
+    let data = match storage().get(&key(upload)).await {
+        Ok(Some(data)) => data,
+        Ok(None) => return Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => {
+            log::error!("Failed to read media {id}: {e}");
+            return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    let mut response = warp::reply::Response::new(Body::from(data));
+    let headers = response.headers_mut();
+    if let Ok(content_type) = HeaderValue::from_str(&content_type) {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    // stored files never change, a new upload gets a new id
+    headers.insert(
+        CACHE_CONTROL,
+        HeaderValue::from_static("private, max-age=86400, immutable"),
+    );
+
+    Ok(response)
+}
+
+/// Reads the `file` and `alt_text` parts, others are ignored
+async fn read_form(form: FormData) -> Result<MediaUploadRequest, Rejection> {
+    let mut req = MediaUploadRequest::default();
+    let mut parts = form;
+
+    while let Some(part) = parts.try_next().await.map_err(form_error)? {
+        match part.name() {
+            "file" => req.file = Some(read_part(part).await?),
+            "alt_text" => {
+                let data = read_part(part).await?;
+                req.alt_text = Some(String::from_utf8_lossy(&data).into_owned());
+            }
+            _ => (),
+        }
+    }
+
+    Ok(req)
+}
+
+async fn read_part(part: Part) -> Result<Bytes, Rejection> {
+    part.stream()
+        .try_fold(BytesMut::new(), |mut data, chunk| async move {
+            data.put(chunk);
+            Ok(data)
+        })
+        .await
+        .map(BytesMut::freeze)
+        .map_err(form_error)
+}
+
+fn form_error(e: warp::Error) -> Rejection {
+    reject::custom(MediaError::Form(e.to_string()))
+}
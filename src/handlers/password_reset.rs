@@ -0,0 +1,171 @@
+use std::{convert::Infallible, sync::Arc};
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect, Set,
+    TransactionTrait,
+};
+use tokio::sync::Mutex;
+use warp::{hyper::StatusCode, Reply};
+
+use entity::{password_reset, user};
+
+use crate::{
+    audit::{self, Entry, Event},
+    errors::db::DbError,
+    handlers::{auth::revoke_user_sessions, users::normalize_identifier},
+    mailer::{self, Mail},
+    models::session::ClientInfo,
+    password,
+    requests::auth::{ForgotPasswordRequest, ResetPasswordRequest},
+    tokens,
+};
+
+/// How long a password reset link stays valid, in seconds
+const RESET_LIFETIME: i64 = 60 * 60;
+
+/// Always accepted, so the response does not tell which addresses are registered
+pub async fn forgot(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: ForgotPasswordRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let user = user::Entity::find()
+        .filter(user::Column::Email.eq(normalize_identifier(&body.email)))
+        .one(&db)
+        .await;
+
+    match user {
+        Ok(Some(user)) => {
+            if let Err(e) = send_reset(&db, &user).await {
+                log::error!("Failed to send password reset to user {}: {e:?}", user.id);
+            }
+        }
+        Ok(None) => (),
+        Err(e) => log::error!("Failed to look up user for password reset: {e}"),
+    }
+
+    Ok(StatusCode::ACCEPTED.into_response())
+}
+
+pub async fn reset(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    client: ClientInfo,
+    body: ResetPasswordRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    match reset_password(&db, body).await {
+        Ok(user_id) => {
+            let entry = Entry::success(Event::PasswordReset)
+                .target(user_id)
+                .client(&client);
+            audit::record(&db, entry).await;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Err(DbError::NotFound) => Ok(warp::reply::with_status(
+            warp::reply::json(&DbError::NotFound),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response()),
+        Err(e) => Ok(warp::reply::with_status(
+            warp::reply::json(&e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .into_response()),
+    }
+}
+
+/// Stores a single-use token and mails the reset link to the user
+///
+/// The mail is delivered in the background
+async fn send_reset(db: &DatabaseConnection, user: &user::Model) -> Result<(), DbError> {
+    let token = tokens::generate();
+    let expires_at = Utc::now().naive_utc() + chrono::Duration::seconds(RESET_LIFETIME);
+
+    password_reset::ActiveModel {
+        token_hash: Set(tokens::hash(&token)),
+        user_id: Set(user.id),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|_| DbError::FailedToAdd)?;
+
+    let mail = Mail {
+        to: user.email.clone(),
+        subject: "Reset your Nova password".to_owned(),
+        body: format!(
+            "Hi {},\n\nsomeone asked to reset the password of your account. If it was you, use the token below within an hour:\n\n{}\n\nIf it was not you, you can ignore this mail.\n",
+            user.username,
+            token
+        ),
+    };
+
+    tokio::spawn(async move {
+        if let Err(e) = mailer::mailer().send(mail).await {
+            log::error!("Failed to send password reset mail: {e}");
+        }
+    });
+
+    Ok(())
+}
+
+/// Sets the new password, uses up every pending reset token of the user
+/// and revokes all of their sessions, answers with the id of the user
+async fn reset_password(
+    db: &DatabaseConnection,
+    body: ResetPasswordRequest,
+) -> Result<i32, DbError> {
+    let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+
+    // the locked token can not be used by a concurrent reset
+    let reset = password_reset::Entity::find_by_id(tokens::hash(&body.token))
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::NotFound)?;
+
+    if reset.expires_at < Utc::now().naive_utc() {
+        return Err(DbError::NotFound);
+    }
+
+    let user = user::Entity::find_by_id(reset.user_id)
+        .one(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::NotFound)?;
+
+    // hashed only for valid tokens, bogus ones are turned away cheaply
+    let hash = password::hash(body.password)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    password_reset::Entity::delete_many()
+        .filter(password_reset::Column::UserId.eq(user.id))
+        .exec(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    let user_id = user.id;
+    // the token arrived by mail, so the address is proven as well
+    let verified = user.email_verified_at.is_some();
+    let mut user: user::ActiveModel = user.into();
+    user.password = Set(hash);
+    if !verified {
+        user.email_verified_at = Set(Some(Utc::now().naive_utc()));
+    }
+    user.update(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    revoke_user_sessions(&txn, user_id).await?;
+
+    txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+    Ok(user_id)
+}
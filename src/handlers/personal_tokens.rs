@@ -0,0 +1,243 @@
+use std::{convert::Infallible, sync::Arc};
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use tokio::sync::Mutex;
+use warp::{hyper::StatusCode, reject, Rejection, Reply};
+
+use entity::{personal_access_token, user};
+
+use crate::{
+    audit::{self, Entry, Event},
+    errors::{
+        db::DbError,
+        jwt::JWTError,
+        validation::{FieldError, ValidationError},
+    },
+    jwt::Claims,
+    models::{
+        actor::Actor,
+        permission::Permission,
+        personal_token::{CreatedPersonalToken, PersonalTokenInfo},
+        session::ClientInfo,
+    },
+    permissions,
+    requests::auth::PersonalTokenCreateRequest,
+    tokens,
+    validation::TOO_LONG,
+};
+
+use super::auth::is_sanctioned;
+
+/// Tells personal access tokens apart from JWTs in the `Authorization` header
+pub const TOKEN_PREFIX: &str = "nova_pat_";
+
+/// How precisely the last use of a token is tracked, in seconds
+const LAST_USED_PRECISION: i64 = 60;
+
+pub async fn list(
+    claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let result = personal_access_token::Entity::find()
+        .filter(personal_access_token::Column::UserId.eq(claims.sub as i32))
+        .filter(personal_access_token::Column::RevokedAt.is_null())
+        .order_by_desc(personal_access_token::Column::CreatedAt)
+        .all(&db)
+        .await;
+
+    let tokens = match result {
+        Ok(tokens) => tokens,
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    let tokens = tokens
+        .into_iter()
+        .map(|token| PersonalTokenInfo {
+            id: token.id,
+            name: token.name,
+            scopes: Permission::parse_list(&token.scopes),
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+        })
+        .collect::<Vec<PersonalTokenInfo>>();
+
+    Ok(warp::reply::json(&tokens).into_response())
+}
+
+pub async fn create(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: PersonalTokenCreateRequest,
+) -> Result<warp::reply::Response, Rejection> {
+    let expires_at = match body.expires_in_days {
+        Some(days) => match Utc::now()
+            .naive_utc()
+            .checked_add_signed(chrono::Duration::days(days.into()))
+        {
+            Some(expires_at) => Some(expires_at),
+            None => {
+                return Err(reject::custom(ValidationError {
+                    errors: vec![FieldError {
+                        field: "expires_in_days",
+                        code: TOO_LONG,
+                        message: "Expires too far in the future".to_string(),
+                    }],
+                }))
+            }
+        },
+        None => None,
+    };
+
+    let db = db_session.lock().await.to_owned();
+
+    let token = format!("{TOKEN_PREFIX}{}", tokens::generate());
+
+    let result = personal_access_token::ActiveModel {
+        user_id: Set(claims.sub as i32),
+        name: Set(body.name),
+        token_hash: Set(tokens::hash(&token)),
+        scopes: Set(Permission::join(&body.scopes)),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await;
+
+    match result {
+        Ok(created) => {
+            let entry = Entry::success(Event::TokenCreated)
+                .actor(created.user_id)
+                .target(created.user_id)
+                .client(&client)
+                .detail(format!("token {} with {}", created.id, created.scopes));
+            audit::record(&db, entry).await;
+
+            Ok(warp::reply::with_status(
+                warp::reply::json(&CreatedPersonalToken {
+                    id: created.id,
+                    name: created.name,
+                    token,
+                    scopes: body.scopes,
+                    expires_at: created.expires_at,
+                }),
+                StatusCode::CREATED,
+            )
+            .into_response())
+        }
+        Err(_) => Ok(warp::reply::with_status(
+            warp::reply::json(&DbError::FailedToAdd),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .into_response()),
+    }
+}
+
+pub async fn revoke(
+    token_id: i32,
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    // tokens of other users are reported as missing
+    let result = personal_access_token::Entity::update_many()
+        .col_expr(
+            personal_access_token::Column::RevokedAt,
+            Utc::now().naive_utc().into(),
+        )
+        .filter(personal_access_token::Column::Id.eq(token_id))
+        .filter(personal_access_token::Column::UserId.eq(claims.sub as i32))
+        .filter(personal_access_token::Column::RevokedAt.is_null())
+        .exec(&db)
+        .await;
+
+    match result {
+        Ok(res) if res.rows_affected == 0 => Ok(StatusCode::NOT_FOUND.into_response()),
+        Ok(_) => {
+            let entry = Entry::success(Event::TokenRevoked)
+                .actor(claims.sub as i32)
+                .target(claims.sub as i32)
+                .client(&client)
+                .detail(format!("token {token_id}"));
+            audit::record(&db, entry).await;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+/// Resolves a personal access token to its owner
+///
+/// The token has to carry the permission as a scope and the role of the owner has to grant it
+pub async fn authenticate(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    token: &str,
+    permission: Permission,
+) -> Result<Actor, JWTError> {
+    let db = db_session.lock().await.to_owned();
+    let now = Utc::now().naive_utc();
+
+    let found = personal_access_token::Entity::find()
+        .filter(personal_access_token::Column::TokenHash.eq(tokens::hash(token)))
+        .find_also_related(user::Entity)
+        .one(&db)
+        .await
+        .map_err(|_| JWTError::JWTTokenError)?;
+
+    let (personal_token, owner) = match found {
+        Some((personal_token, Some(owner)))
+            if personal_token.revoked_at.is_none()
+                && personal_token.expires_at.is_none_or(|at| at > now) =>
+        {
+            (personal_token, owner)
+        }
+        _ => return Err(JWTError::JWTTokenError),
+    };
+
+    if is_sanctioned(&owner, now) {
+        return Err(JWTError::AccountSanctionedError);
+    }
+
+    if !Permission::parse_list(&personal_token.scopes).contains(&permission) {
+        return Err(JWTError::MissingScopeError);
+    }
+
+    // scopes never reach beyond the current permissions of the owner
+    let role = permissions::role(&db, owner.role.into())
+        .await
+        .map_err(|_| JWTError::JWTTokenError)?;
+    let actor = Actor {
+        id: owner.id,
+        permissions: Permission::parse_list(&personal_token.scopes)
+            .into_iter()
+            .filter(|scope| role.permissions.contains(scope))
+            .collect(),
+    };
+    if !actor.can(permission) {
+        return Err(JWTError::NoPermissionError);
+    }
+
+    // bots call often, the last use is only tracked to the minute
+    let stale = personal_token
+        .last_used_at
+        .is_none_or(|at| at + chrono::Duration::seconds(LAST_USED_PRECISION) < now);
+    if stale {
+        let token_id = personal_token.id;
+        let mut personal_token: personal_access_token::ActiveModel = personal_token.into();
+        personal_token.last_used_at = Set(Some(now));
+        if personal_token.update(&db).await.is_err() {
+            log::error!("Failed to update the last use of personal access token {token_id}");
+        }
+    }
+
+    Ok(actor)
+}
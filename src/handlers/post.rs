@@ -1,22 +1,67 @@
-use std::{convert::Infallible, sync::Arc};
+use std::{collections::HashMap, convert::Infallible, sync::Arc};
 
-use entity::{follower, post, post_like, prelude::Post};
-use migration::{Alias, DbErr, JoinType, Order};
+use chrono::{Duration, Utc};
+use entity::{
+    follower, media, post, post_like, post_moderation, post_revision, prelude::Post, user,
+};
+use migration::{Alias, DbErr, Expr, JoinType, Order};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
-    QueryOrder, QuerySelect, RelationTrait, Set, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DatabaseTransaction,
+    DbBackend, EntityTrait, ModelTrait, QueryFilter, QueryOrder, QuerySelect, RelationTrait, Set,
+    Statement, TransactionTrait,
 };
 use tokio::sync::Mutex;
-use warp::{hyper::StatusCode, Reply};
+use warp::{hyper::StatusCode, reject, Rejection, Reply};
+
+use crate::{
+    audit::{self, Entry, Event},
+    errors::{
+        access::AccessError,
+        validation::{FieldError, ValidationError},
+    },
+    media as uploads,
+    models::{
+        actor::Actor,
+        media::MediaInfo,
+        permission::Permission,
+        post::{FeedItem, PostDetail, PostKind, PostView, ReplyTree, Reposter, Thread},
+        session::ClientInfo,
+    },
+    requests::post::{
+        create::PostCreateRequest,
+        thread::{RepliesQuery, ThreadQuery},
+    },
+    validation::UNAVAILABLE,
+};
+
+/// Actions recorded when a moderator changes a post of another user
+const MODERATION_UPDATE: &str = "update";
+const MODERATION_DELETE: &str = "delete";
+
+/// Minutes authors may edit their posts for by default
+const POST_EDIT_WINDOW_MINUTES: i64 = 60;
 
-use crate::requests::post::create::PostCreateRequest;
+/// Replies returned per page by default and at most
+const REPLIES_PAGE_SIZE: u64 = 20;
+const REPLIES_PAGE_SIZE_MAX: u64 = 100;
+
+/// Levels of replies in a thread by default and at most
+const THREAD_DEPTH: u32 = 3;
+const THREAD_DEPTH_MAX: u32 = 10;
+
+/// Replies loaded into one thread, the deepest levels are cut first
+const THREAD_REPLIES_MAX: u64 = 500;
 
 pub async fn list(
     _id_from_token: i32,
     db_session: Arc<Mutex<DatabaseConnection>>,
 ) -> Result<impl warp::Reply, Infallible> {
     let db = db_session.lock().await.to_owned();
-    let posts: Vec<post::Model> = post::Entity::find().all(&db).await.unwrap();
+    let posts: Vec<post::Model> = post::Entity::find()
+        .filter(post::Column::DeletedAt.is_null())
+        .all(&db)
+        .await
+        .unwrap();
 
     Ok(warp::reply::json(&posts))
 }
@@ -44,6 +89,7 @@ pub async fn list_feed(
 
     let posts: Vec<post::Model> = post::Entity::find()
         .filter(post::Column::UserId.is_in(followings))
+        .filter(post::Column::DeletedAt.is_null())
         .join_as(
             JoinType::LeftJoin,
             post::Relation::User.def(),
@@ -54,7 +100,10 @@ pub async fn list_feed(
         .await
         .unwrap();
 
-    Ok(warp::reply::json(&posts).into_response())
+    match feed_items(&db, posts).await {
+        Ok(items) => Ok(warp::reply::json(&items).into_response()),
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
 }
 
 pub async fn get_by_id(
@@ -64,18 +113,27 @@ pub async fn get_by_id(
 ) -> Result<warp::reply::Response, Infallible> {
     // Just return a JSON object of user
     let db = db_session.lock().await.to_owned();
-    let post = post::Entity::find_by_id(id).one(&db).await;
+    let post = post::Entity::find_by_id(id)
+        .find_with_related(media::Entity)
+        .order_by_asc(media::Column::Id)
+        .all(&db)
+        .await;
 
     if post.is_err() {
         return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
     }
 
-    let post = post.unwrap();
+    let post = post.unwrap().into_iter().next();
     if post.is_none() {
         return Ok(StatusCode::NOT_FOUND.into_response());
     }
 
-    Ok(warp::reply::json(&post.unwrap()).into_response())
+    let (post, media) = post.unwrap();
+    let post = PostDetail {
+        post: PostView::from(post),
+        media: media.into_iter().map(MediaInfo::from).collect(),
+    };
+    Ok(warp::reply::json(&post).into_response())
 }
 
 pub async fn like(
@@ -85,15 +143,8 @@ pub async fn like(
 ) -> Result<impl warp::Reply, Infallible> {
     let db = db_session.lock().await.to_owned();
 
-    let post_like = post_like::Entity::find_by_id((_id_from_token, post_id))
-        .one(&db)
-        .await;
-
-    if post_like.is_err() {
-        return Ok(StatusCode::INTERNAL_SERVER_ERROR);
-    }
-
-    let post = post::Entity::find_by_id(post_id).one(&db).await;
+    // likes of a repost go to the reposted post
+    let post = find_shared(&db, post_id).await;
     if post.is_err() {
         return Ok(StatusCode::INTERNAL_SERVER_ERROR);
     }
@@ -103,7 +154,18 @@ pub async fn like(
         return Ok(StatusCode::NOT_FOUND);
     }
 
-    let mut post_model: post::ActiveModel = post.unwrap().into();
+    let post = post.unwrap();
+    let post_id = post.id;
+
+    let post_like = post_like::Entity::find_by_id((_id_from_token, post_id))
+        .one(&db)
+        .await;
+
+    if post_like.is_err() {
+        return Ok(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let mut post_model: post::ActiveModel = post.into();
     match post_like.unwrap() {
         Some(item) => {
             let txn = db.begin().await.unwrap();
@@ -138,36 +200,140 @@ pub async fn like(
     }
 }
 
+pub async fn repost(
+    post_id: i32,
+    id_from_token: i32,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let result = async {
+        let txn = db.begin().await?;
+
+        // reposting a repost shares the reposted post
+        let Some(shared) = find_shared(&txn, post_id).await? else {
+            return Ok(StatusCode::NOT_FOUND);
+        };
+
+        let repost = post::Entity::find()
+            .filter(post::Column::UserId.eq(id_from_token))
+            .filter(post::Column::RelatedToPost.eq(shared.id))
+            .filter(post::Column::Kind.eq(PostKind::Repost.as_str()))
+            .one(&txn)
+            .await?;
+
+        let status = match repost {
+            Some(repost) => {
+                repost.delete(&txn).await?;
+                change_counter(&txn, shared.id, post::Column::Reposts, -1).await?;
+                StatusCode::OK
+            }
+            None => {
+                if !change_counter(&txn, shared.id, post::Column::Reposts, 1).await? {
+                    return Ok(StatusCode::NOT_FOUND);
+                }
+                post::ActiveModel {
+                    user_id: Set(id_from_token),
+                    related_to_post: Set(Some(shared.id)),
+                    kind: Set(PostKind::Repost.as_str().to_string()),
+                    text: Set(String::new()),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?;
+                StatusCode::CREATED
+            }
+        };
+
+        txn.commit().await?;
+        Ok::<StatusCode, DbErr>(status)
+    }
+    .await;
+
+    match result {
+        Ok(status) => Ok(status),
+        Err(_e) => Ok(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
 pub async fn create(
-    _id_from_token: i32,
+    id_from_token: i32,
     db_session: Arc<Mutex<DatabaseConnection>>,
     req: PostCreateRequest,
-) -> Result<impl warp::Reply, Infallible> {
+) -> Result<warp::reply::Response, Rejection> {
     // Just return a JSON object of user
     let db = db_session.lock().await.to_owned();
-    let post = post::ActiveModel {
-        user_id: Set(req.user_id),
-        related_to_post: Set(req.related_to_post),
-        text: Set(req.text),
-        ..Default::default()
+
+    let (kind, related_to_post) = match (req.related_to_post, req.quote_of) {
+        (Some(post_id), _) => (PostKind::Reply, Some(post_id)),
+        (None, Some(post_id)) => (PostKind::Quote, Some(post_id)),
+        (None, None) => (PostKind::Original, None),
     };
 
-    match post.insert(&db).await {
-        Ok(_) => Ok(StatusCode::CREATED),
-        Err(_e) => Ok(StatusCode::INTERNAL_SERVER_ERROR),
+    let result = async {
+        let txn = db.begin().await?;
+
+        // replies and quotes of a repost go to the reposted post,
+        // counting them first locks it until the new post is in
+        let related_to_post = match related_to_post {
+            Some(post_id) => match find_shared(&txn, post_id).await? {
+                Some(related) => Some(related.id),
+                None => return Ok(Some(StatusCode::NOT_FOUND)),
+            },
+            None => None,
+        };
+        if let (Some(related_id), Some(counter)) = (related_to_post, counter_of(kind)) {
+            if !change_counter(&txn, related_id, counter, 1).await? {
+                return Ok(Some(StatusCode::NOT_FOUND));
+            }
+        }
+
+        let post = post::ActiveModel {
+            user_id: Set(id_from_token),
+            related_to_post: Set(related_to_post),
+            kind: Set(kind.as_str().to_string()),
+            text: Set(req.text),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        if !attach_media(&txn, post.id, id_from_token, req.media_ids).await? {
+            return Ok(None);
+        }
+
+        txn.commit().await?;
+        Ok::<Option<StatusCode>, DbErr>(Some(StatusCode::CREATED))
+    }
+    .await;
+
+    match result {
+        Ok(Some(status)) => Ok(status.into_response()),
+        Ok(None) => Err(reject::custom(ValidationError {
+            errors: vec![FieldError {
+                field: "media_ids",
+                code: UNAVAILABLE,
+                message: "Only own uploads not attached to another post".to_string(),
+            }],
+        })),
+        Err(_e) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
     }
 }
 
 pub async fn update(
     id: i32,
-    _id_from_token: i32,
+    actor: Actor,
+    client: ClientInfo,
     db_session: Arc<Mutex<DatabaseConnection>>,
     req: PostCreateRequest,
-) -> Result<warp::reply::Response, Infallible> {
+) -> Result<warp::reply::Response, Rejection> {
     // Just return a JSON object of user
     let db = db_session.lock().await.to_owned();
 
-    let post = post::Entity::find_by_id(id).one(&db).await;
+    let post = post::Entity::find_by_id(id)
+        .filter(post::Column::DeletedAt.is_null())
+        .one(&db)
+        .await;
 
     if post.is_err() {
         return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
@@ -178,11 +344,66 @@ pub async fn update(
         return Ok(StatusCode::NOT_FOUND.into_response());
     }
 
-    let mut post: post::ActiveModel = post.unwrap().into();
-    post.text = Set(req.text);
+    let post = post.unwrap();
+    let overridden = check_author(&post, &actor)?;
+
+    // reposts have no text to edit
+    if PostKind::of(&post) == PostKind::Repost {
+        return Ok(StatusCode::UNPROCESSABLE_ENTITY.into_response());
+    }
+
+    // moderators may still change the post afterwards
+    if !overridden && !within_edit_window(&post) {
+        return Err(reject::custom(AccessError::EditWindowClosed));
+    }
+
+    let result = async {
+        let txn = db.begin().await?;
+
+        // the locked row holds the text of a concurrent edit
+        let post = post::Entity::find_by_id(id)
+            .filter(post::Column::DeletedAt.is_null())
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(DbErr::RecordNotFound(format!("post {id}")))?;
+        if post.text == req.text {
+            return Ok((post, false));
+        }
+
+        // the text a moderator replaced is kept in the moderation log only,
+        // revisions are readable by everyone
+        if overridden {
+            record_moderation(&txn, &post, &actor, MODERATION_UPDATE).await?;
+        } else {
+            post_revision::ActiveModel {
+                post_id: Set(post.id),
+                text: Set(post.text.clone()),
+                editor_id: Set(actor.id),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        let edit_count = post.edit_count + 1;
+        let mut post: post::ActiveModel = post.into();
+        post.text = Set(req.text);
+        post.edited_at = Set(Some(Utc::now().naive_utc()));
+        post.edit_count = Set(edit_count);
+        let post = post.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok::<(post::Model, bool), DbErr>((post, overridden))
+    }
+    .await;
+
+    match result {
+        Ok((post, moderated)) => {
+            if moderated {
+                record_moderation_event(&db, &post, &actor, &client, MODERATION_UPDATE).await;
+            }
 
-    match post.update(&db).await {
-        Ok(post) => {
             Ok(warp::reply::with_status(warp::reply::json(&post), StatusCode::OK).into_response())
         }
         Err(_e) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
@@ -191,13 +412,17 @@ pub async fn update(
 
 pub async fn delete(
     id: i32,
-    _id_from_token: i32,
+    actor: Actor,
+    client: ClientInfo,
     db_session: Arc<Mutex<DatabaseConnection>>,
-) -> Result<warp::reply::Response, Infallible> {
+) -> Result<warp::reply::Response, Rejection> {
     // Just return a JSON object of user
     let db = db_session.lock().await.to_owned();
 
-    let post = post::Entity::find_by_id(id).one(&db).await;
+    let post = post::Entity::find_by_id(id)
+        .filter(post::Column::DeletedAt.is_null())
+        .one(&db)
+        .await;
 
     if post.is_err() {
         return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
@@ -209,8 +434,524 @@ pub async fn delete(
         return Ok(StatusCode::NOT_FOUND.into_response());
     }
 
-    match Post::delete_by_id(id).exec(&db).await {
-        Ok(_) => Ok(StatusCode::OK.into_response()),
+    let post = post.unwrap();
+    let overridden = check_author(&post, &actor)?;
+
+    let result = async {
+        let txn = db.begin().await?;
+        if overridden {
+            record_moderation(&txn, &post, &actor, MODERATION_DELETE).await?;
+        }
+
+        // reposts only point at the post, so they go with it
+        post::Entity::delete_many()
+            .filter(post::Column::RelatedToPost.eq(id))
+            .filter(post::Column::Kind.eq(PostKind::Repost.as_str()))
+            .exec(&txn)
+            .await?;
+
+        // a concurrent delete leaves nothing to do
+        let removed = match PostKind::of(&post) {
+            PostKind::Repost => {
+                let result = Post::delete_by_id(id).exec(&txn).await?;
+                (result.rows_affected > 0).then(Vec::new)
+            }
+            _ => tombstone(&txn, id).await?,
+        };
+        let Some(uploads) = removed else {
+            return Ok(None);
+        };
+
+        let counter = counter_of(PostKind::of(&post));
+        if let (Some(related_id), Some(counter)) = (post.related_to_post, counter) {
+            change_counter(&txn, related_id, counter, -1).await?;
+        }
+
+        txn.commit().await?;
+        Ok::<Option<Vec<media::Model>>, DbErr>(Some(uploads))
+    }
+    .await;
+
+    match result {
+        Ok(Some(uploads)) => {
+            if overridden {
+                record_moderation_event(&db, &post, &actor, &client, MODERATION_DELETE).await;
+            }
+
+            uploads::remove_files(&uploads).await;
+            Ok(StatusCode::OK.into_response())
+        }
+        Ok(None) => Ok(StatusCode::NOT_FOUND.into_response()),
         Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
     }
 }
+
+pub async fn list_replies(
+    id: i32,
+    _id_from_token: i32,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    query: RepliesQuery,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    match post::Entity::find_by_id(id).one(&db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+
+    let mut select = post::Entity::find()
+        .filter(post::Column::RelatedToPost.eq(id))
+        .filter(post::Column::Kind.eq(PostKind::Reply.as_str()));
+    if let Some(after) = query.after {
+        select = select.filter(post::Column::Id.gt(after));
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(REPLIES_PAGE_SIZE)
+        .clamp(1, REPLIES_PAGE_SIZE_MAX);
+
+    let replies = select
+        .order_by_asc(post::Column::Id)
+        .limit(limit)
+        .all(&db)
+        .await;
+
+    match replies {
+        Ok(replies) => Ok(warp::reply::json(
+            &replies
+                .into_iter()
+                .map(PostView::from)
+                .collect::<Vec<PostView>>(),
+        )
+        .into_response()),
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+pub async fn get_thread(
+    id: i32,
+    _id_from_token: i32,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    query: ThreadQuery,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let post = match post::Entity::find_by_id(id).one(&db).await {
+        Ok(Some(post)) => post,
+        Ok(None) => return Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    };
+
+    let depth = query
+        .depth
+        .unwrap_or(THREAD_DEPTH)
+        .clamp(1, THREAD_DEPTH_MAX);
+
+    let result = async {
+        let ancestors = find_ancestors(&db, &post).await?;
+        let replies = find_replies(&db, post.id, depth).await?;
+        Ok::<(Vec<post::Model>, Vec<post::Model>), DbErr>((ancestors, replies))
+    }
+    .await;
+
+    match result {
+        Ok((ancestors, replies)) => {
+            let mut children: HashMap<i32, Vec<post::Model>> = HashMap::new();
+            for reply in replies {
+                if let Some(parent_id) = reply.related_to_post {
+                    children.entry(parent_id).or_default().push(reply);
+                }
+            }
+
+            let thread = Thread {
+                ancestors: ancestors.into_iter().map(PostView::from).collect(),
+                replies: reply_tree(&mut children, post.id),
+                post: PostView::from(post),
+            };
+            Ok(warp::reply::json(&thread).into_response())
+        }
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+/// Posts above the given one, from the root down to the parent
+async fn find_ancestors(
+    db: &DatabaseConnection,
+    post: &post::Model,
+) -> Result<Vec<post::Model>, DbErr> {
+    let parent_id = match (PostKind::of(post), post.related_to_post) {
+        (PostKind::Reply, Some(parent_id)) => parent_id,
+        _ => return Ok(Vec::new()),
+    };
+
+    post::Entity::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"WITH RECURSIVE ancestor AS (
+                SELECT p.*, 0 AS level FROM post p WHERE p.id = $1
+                UNION ALL
+                SELECT p.*, a.level + 1 FROM post p
+                JOIN ancestor a ON p.id = a.related_to_post
+                WHERE a.kind = 'reply'
+            )
+            SELECT * FROM ancestor ORDER BY level DESC"#,
+            [parent_id.into()],
+        ))
+        .all(db)
+        .await
+}
+
+/// Replies below the post down to `depth` levels, level by level
+async fn find_replies(
+    db: &DatabaseConnection,
+    post_id: i32,
+    depth: u32,
+) -> Result<Vec<post::Model>, DbErr> {
+    post::Entity::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"WITH RECURSIVE reply AS (
+                SELECT p.*, 1 AS level FROM post p
+                WHERE p.related_to_post = $1 AND p.kind = 'reply'
+                UNION ALL
+                SELECT p.*, r.level + 1 FROM post p
+                JOIN reply r ON p.related_to_post = r.id
+                WHERE p.kind = 'reply' AND r.level < $2
+            )
+            SELECT * FROM reply ORDER BY level, id LIMIT $3"#,
+            [
+                post_id.into(),
+                (depth as i32).into(),
+                (THREAD_REPLIES_MAX as i64).into(),
+            ],
+        ))
+        .all(db)
+        .await
+}
+
+/// Nests the loaded replies below their parents, oldest first
+fn reply_tree(children: &mut HashMap<i32, Vec<post::Model>>, parent_id: i32) -> Vec<ReplyTree> {
+    children
+        .remove(&parent_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|post| ReplyTree {
+            replies: reply_tree(children, post.id),
+            post: PostView::from(post),
+        })
+        .collect()
+}
+
+/// Puts the reposted posts in place of the reposts and adds the quoted posts
+async fn feed_items(
+    db: &DatabaseConnection,
+    posts: Vec<post::Model>,
+) -> Result<Vec<FeedItem>, DbErr> {
+    let reposts = posts
+        .iter()
+        .filter(|post| PostKind::of(post) == PostKind::Repost)
+        .collect::<Vec<&post::Model>>();
+
+    let reposted = find_posts(
+        db,
+        reposts
+            .iter()
+            .filter_map(|post| post.related_to_post)
+            .collect(),
+    )
+    .await?;
+    let reposters = user::Entity::find()
+        .filter(user::Column::Id.is_in(reposts.iter().map(|post| post.user_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|user| (user.id, user.username))
+        .collect::<HashMap<i32, String>>();
+
+    let shown = posts
+        .into_iter()
+        .filter_map(|post| match PostKind::of(&post) {
+            PostKind::Repost => {
+                let original = reposted
+                    .get(&post.related_to_post?)
+                    .filter(|original| original.deleted_at.is_none())?
+                    .clone();
+                let reposter = Reposter {
+                    user_id: post.user_id,
+                    username: reposters.get(&post.user_id).cloned().unwrap_or_default(),
+                    reposted_at: post.created_at,
+                };
+                Some((original, Some(reposter)))
+            }
+            _ => Some((post, None)),
+        })
+        .collect::<Vec<(post::Model, Option<Reposter>)>>();
+
+    let quoted = find_posts(
+        db,
+        shown
+            .iter()
+            .filter(|(post, _)| PostKind::of(post) == PostKind::Quote)
+            .filter_map(|(post, _)| post.related_to_post)
+            .collect(),
+    )
+    .await?;
+
+    let mut media = HashMap::<i32, Vec<MediaInfo>>::new();
+    for upload in media::Entity::find()
+        .filter(media::Column::PostId.is_in(shown.iter().map(|(post, _)| post.id)))
+        .order_by_asc(media::Column::Id)
+        .all(db)
+        .await?
+    {
+        if let Some(post_id) = upload.post_id {
+            media
+                .entry(post_id)
+                .or_default()
+                .push(MediaInfo::from(upload));
+        }
+    }
+
+    Ok(shown
+        .into_iter()
+        .map(|(post, reposted_by)| FeedItem {
+            media: media.remove(&post.id).unwrap_or_default(),
+            quoted_post: match PostKind::of(&post) {
+                PostKind::Quote => post
+                    .related_to_post
+                    .and_then(|id| quoted.get(&id).cloned())
+                    .map(PostView::from),
+                _ => None,
+            },
+            post,
+            reposted_by,
+        })
+        .collect())
+}
+
+async fn find_posts(
+    db: &DatabaseConnection,
+    ids: Vec<i32>,
+) -> Result<HashMap<i32, post::Model>, DbErr> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    Ok(post::Entity::find()
+        .filter(post::Column::Id.is_in(ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|post| (post.id, post))
+        .collect())
+}
+
+/// The post behind the id, a repost stands for the post it shares
+///
+/// Deleted posts can not be shared
+async fn find_shared<C: ConnectionTrait>(db: &C, id: i32) -> Result<Option<post::Model>, DbErr> {
+    let post = find_live(db, id).await?;
+
+    match post {
+        Some(post) if PostKind::of(&post) == PostKind::Repost => match post.related_to_post {
+            Some(shared_id) => find_live(db, shared_id).await,
+            None => Ok(None),
+        },
+        post => Ok(post),
+    }
+}
+
+async fn find_live<C: ConnectionTrait>(db: &C, id: i32) -> Result<Option<post::Model>, DbErr> {
+    post::Entity::find_by_id(id)
+        .filter(post::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+}
+
+/// Scrubs the post into a tombstone, returns `None` when it was already deleted
+///
+/// The row stays so replies keep their place in the thread,
+/// the removed uploads are returned so their files can go once committed
+async fn tombstone(txn: &DatabaseTransaction, id: i32) -> Result<Option<Vec<media::Model>>, DbErr> {
+    let result = post::Entity::update_many()
+        .col_expr(post::Column::Text, "".into())
+        .col_expr(post::Column::Likes, 0.into())
+        .col_expr(post::Column::Reposts, 0.into())
+        .col_expr(post::Column::DeletedAt, Utc::now().naive_utc().into())
+        .filter(post::Column::Id.eq(id))
+        .filter(post::Column::DeletedAt.is_null())
+        .exec(txn)
+        .await?;
+
+    if result.rows_affected == 0 {
+        return Ok(None);
+    }
+
+    post_like::Entity::delete_many()
+        .filter(post_like::Column::PostId.eq(id))
+        .exec(txn)
+        .await?;
+    post_revision::Entity::delete_many()
+        .filter(post_revision::Column::PostId.eq(id))
+        .exec(txn)
+        .await?;
+
+    let uploads = media::Entity::find()
+        .filter(media::Column::PostId.eq(id))
+        .all(txn)
+        .await?;
+    media::Entity::delete_many()
+        .filter(media::Column::PostId.eq(id))
+        .exec(txn)
+        .await?;
+
+    Ok(Some(uploads))
+}
+
+/// Attaches own uploads to the new post, false when one of them is not available
+async fn attach_media(
+    txn: &DatabaseTransaction,
+    post_id: i32,
+    user_id: i32,
+    mut media_ids: Vec<i32>,
+) -> Result<bool, DbErr> {
+    media_ids.sort_unstable();
+    media_ids.dedup();
+    if media_ids.is_empty() {
+        return Ok(true);
+    }
+
+    let result = media::Entity::update_many()
+        .col_expr(media::Column::PostId, post_id.into())
+        .filter(media::Column::Id.is_in(media_ids.clone()))
+        .filter(media::Column::UserId.eq(user_id))
+        .filter(media::Column::PostId.is_null())
+        .exec(txn)
+        .await?;
+
+    Ok(result.rows_affected == media_ids.len() as u64)
+}
+
+/// Counter of the related post a post of the kind is counted in
+fn counter_of(kind: PostKind) -> Option<post::Column> {
+    match kind {
+        PostKind::Original => None,
+        PostKind::Reply => Some(post::Column::Comments),
+        PostKind::Repost => Some(post::Column::Reposts),
+        PostKind::Quote => Some(post::Column::Quotes),
+    }
+}
+
+/// Moves a counter of the post, false when there is no such post
+async fn change_counter(
+    txn: &DatabaseTransaction,
+    post_id: i32,
+    counter: post::Column,
+    by: i32,
+) -> Result<bool, DbErr> {
+    let result = post::Entity::update_many()
+        .col_expr(counter, Expr::col(counter).add(by))
+        .filter(post::Column::Id.eq(post_id))
+        .exec(txn)
+        .await?;
+
+    Ok(result.rows_affected > 0)
+}
+
+pub async fn list_revisions(
+    id: i32,
+    _id_from_token: i32,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let revisions = post::Entity::find_by_id(id)
+        .filter(post::Column::DeletedAt.is_null())
+        .find_with_related(post_revision::Entity)
+        .order_by_desc(post_revision::Column::Id)
+        .all(&db)
+        .await;
+
+    match revisions.map(|posts| posts.into_iter().next()) {
+        Ok(Some((_, revisions))) => Ok(warp::reply::json(&revisions).into_response()),
+        Ok(None) => Ok(StatusCode::NOT_FOUND.into_response()),
+        Err(_) => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+    }
+}
+
+/// Minutes after creation the author may edit a post for
+///
+/// Set with `POST_EDIT_WINDOW_MINUTES`, `0` lets authors edit at any time
+fn edit_window() -> Option<Duration> {
+    let minutes = std::env::var("POST_EDIT_WINDOW_MINUTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(POST_EDIT_WINDOW_MINUTES);
+
+    match minutes {
+        0 => None,
+        minutes => Some(Duration::minutes(minutes)),
+    }
+}
+
+fn within_edit_window(post: &post::Model) -> bool {
+    edit_window().is_none_or(|window| Utc::now().naive_utc() < post.created_at + window)
+}
+
+/// Lets the author through, and moderators as an override
+///
+/// Returns whether the change is an override that has to be recorded
+fn check_author(post: &post::Model, actor: &Actor) -> Result<bool, Rejection> {
+    if post.user_id == actor.id {
+        return Ok(false);
+    }
+
+    match actor.can(Permission::PostsModerate) {
+        true => Ok(true),
+        false => Err(reject::custom(AccessError::NotAuthor)),
+    }
+}
+
+async fn record_moderation(
+    txn: &DatabaseTransaction,
+    post: &post::Model,
+    actor: &Actor,
+    action: &str,
+) -> Result<(), DbErr> {
+    log::info!(
+        "Moderator {} applied {action} to post {} of user {}",
+        actor.id,
+        post.id,
+        post.user_id
+    );
+
+    post_moderation::ActiveModel {
+        post_id: Set(post.id),
+        author_id: Set(post.user_id),
+        moderator_id: Set(Some(actor.id)),
+        action: Set(action.to_string()),
+        previous_text: Set(post.text.clone()),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await
+    .map(|_| ())
+}
+
+/// Adds a moderator override to the audit log once it is committed
+async fn record_moderation_event(
+    db: &DatabaseConnection,
+    post: &post::Model,
+    actor: &Actor,
+    client: &ClientInfo,
+    action: &str,
+) {
+    let entry = Entry::success(Event::PostModerated)
+        .actor(actor.id)
+        .target(post.user_id)
+        .client(client)
+        .detail(format!("{action} of post {}", post.id));
+    audit::record(db, entry).await;
+}
@@ -0,0 +1,459 @@
+use std::{convert::Infallible, sync::Arc};
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, EntityTrait,
+    QueryFilter, QuerySelect, Set, TransactionTrait,
+};
+use tokio::sync::Mutex;
+use warp::{hyper::StatusCode, Rejection, Reply};
+
+use entity::{login_challenge, recovery_code, user, user_totp};
+
+use crate::{
+    audit::{self, Entry, Event},
+    cookies,
+    errors::db::DbError,
+    jwt::Claims,
+    models::{
+        session::ClientInfo,
+        two_factor::{RecoveryCodes, TotpEnrollment, TwoFactorChallenge},
+    },
+    requests::auth::{TwoFactorCodeRequest, TwoFactorLoginRequest},
+    throttle, tokens, totp,
+};
+
+use super::auth::add_jwt_session;
+
+/// How long a login challenge stays valid, in seconds
+const CHALLENGE_LIFETIME: i64 = 60 * 5;
+
+/// Wrong codes a login challenge survives, a new login is needed afterwards
+const CHALLENGE_ATTEMPTS: i16 = 5;
+
+/// Starts an enrollment, replacing a pending one
+pub async fn enroll(
+    claims: Claims,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    match start_enrollment(&db, claims.sub as i32).await {
+        Ok(enrollment) => Ok(warp::reply::json(&enrollment).into_response()),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Enables 2FA once the user proved the app is set up, answers with recovery codes
+pub async fn confirm(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: TwoFactorCodeRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+    let user_id = claims.sub as i32;
+
+    match confirm_enrollment(&db, user_id, &body.code).await {
+        Ok(codes) => {
+            let entry = Entry::success(Event::TwoFactorEnabled)
+                .actor(user_id)
+                .target(user_id)
+                .client(&client);
+            audit::record(&db, entry).await;
+
+            Ok(warp::reply::json(&codes).into_response())
+        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Replaces every recovery code of the user
+pub async fn regenerate_recovery_codes(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: TwoFactorCodeRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+    let user_id = claims.sub as i32;
+
+    let result = async {
+        let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+        if !check_code(&txn, user_id, &body.code).await? {
+            return Err(DbError::WrongCredentials);
+        }
+        let codes = replace_recovery_codes(&txn, user_id).await?;
+        txn.commit().await.map_err(|_| DbError::InternalError)?;
+        Ok(codes)
+    }
+    .await;
+
+    match result {
+        Ok(codes) => {
+            let entry = Entry::success(Event::RecoveryCodesRegenerated)
+                .actor(user_id)
+                .target(user_id)
+                .client(&client);
+            audit::record(&db, entry).await;
+
+            Ok(warp::reply::json(&codes).into_response())
+        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+pub async fn disable(
+    claims: Claims,
+    client: ClientInfo,
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: TwoFactorCodeRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+    let user_id = claims.sub as i32;
+
+    let result = async {
+        let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+        if !check_code(&txn, user_id, &body.code).await? {
+            return Err(DbError::WrongCredentials);
+        }
+        user_totp::Entity::delete_by_id(user_id)
+            .exec(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+        recovery_code::Entity::delete_many()
+            .filter(recovery_code::Column::UserId.eq(user_id))
+            .exec(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+        txn.commit().await.map_err(|_| DbError::InternalError)
+    }
+    .await;
+
+    match result {
+        Ok(_) => {
+            let entry = Entry::success(Event::TwoFactorDisabled)
+                .actor(user_id)
+                .target(user_id)
+                .client(&client);
+            audit::record(&db, entry).await;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Exchanges a login challenge and a code for tokens
+///
+/// Wrong codes count against the lockout of the account like wrong passwords
+pub async fn login(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    client: ClientInfo,
+    body: TwoFactorLoginRequest,
+) -> Result<warp::reply::Response, Rejection> {
+    let db = db_session.lock().await.to_owned();
+
+    let account = match challenge_owner(&db, &body.challenge).await {
+        Ok(owner) => owner.map(throttle::user_key),
+        Err(e) => return Ok(error_response(e)),
+    };
+    if let Some(account) = &account {
+        throttle::count_attempt(account).await?;
+    }
+
+    let result = match answer_challenge(&db, &body, &client).await {
+        Ok(user) => add_jwt_session(db_session, &user, client).await,
+        Err(e) => Err(e),
+    };
+
+    // the attempt stays counted as failed unless the second factor passed
+    if let (Some(account), Ok(_)) = (&account, &result) {
+        throttle::clear_failures(account).await;
+    }
+
+    match result {
+        Ok(tokens) if body.cookie && cookies::enabled() => Ok(cookies::session_reply(tokens)),
+        Ok(tokens) => Ok(warp::reply::json(&tokens).into_response()),
+        Err(DbError::NotFound) => Ok(error_response(DbError::WrongCredentials)),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Whether the user has to pass a second factor on login
+pub async fn is_enabled(db: &DatabaseConnection, user_id: i32) -> Result<bool, DbError> {
+    user_totp::Entity::find_by_id(user_id)
+        .filter(user_totp::Column::ConfirmedAt.is_not_null())
+        .one(db)
+        .await
+        .map(|totp| totp.is_some())
+        .map_err(|_| DbError::InternalError)
+}
+
+/// Creates the challenge a login of an account with 2FA enabled is finished with
+pub async fn create_challenge(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<TwoFactorChallenge, DbError> {
+    let now = Utc::now().naive_utc();
+
+    login_challenge::Entity::delete_many()
+        .filter(login_challenge::Column::UserId.eq(user_id))
+        .filter(login_challenge::Column::ExpiresAt.lt(now))
+        .exec(db)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    let challenge = tokens::generate();
+    let expires_at = now + chrono::Duration::seconds(CHALLENGE_LIFETIME);
+
+    login_challenge::ActiveModel {
+        token_hash: Set(tokens::hash(&challenge)),
+        user_id: Set(user_id),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|_| DbError::FailedToAdd)?;
+
+    Ok(TwoFactorChallenge {
+        challenge,
+        expires_in: CHALLENGE_LIFETIME,
+    })
+}
+
+async fn start_enrollment(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<TotpEnrollment, DbError> {
+    let user = user::Entity::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::NotFound)?;
+
+    let existing = user_totp::Entity::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    if existing
+        .as_ref()
+        .is_some_and(|totp| totp.confirmed_at.is_some())
+    {
+        return Err(DbError::AlreadyExists);
+    }
+
+    let secret = totp::generate_secret();
+    let otpauth_uri = totp::otpauth_uri(&secret, &user.username).map_err(|e| {
+        log::error!("Failed to build otpauth URI: {e}");
+        DbError::InternalError
+    })?;
+
+    let pending = user_totp::ActiveModel {
+        user_id: Set(user_id),
+        secret: Set(secret.clone()),
+        created_at: Set(Utc::now().naive_utc()),
+        confirmed_at: Set(None),
+        last_used_step: Set(None),
+    };
+
+    match existing {
+        Some(_) => pending.update(db).await,
+        None => pending.insert(db).await,
+    }
+    .map_err(|_| DbError::FailedToAdd)?;
+
+    Ok(TotpEnrollment {
+        secret,
+        otpauth_uri,
+    })
+}
+
+async fn confirm_enrollment(
+    db: &DatabaseConnection,
+    user_id: i32,
+    code: &str,
+) -> Result<RecoveryCodes, DbError> {
+    let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+
+    let pending = user_totp::Entity::find_by_id(user_id)
+        .filter(user_totp::Column::ConfirmedAt.is_null())
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::NotFound)?;
+
+    let step = totp::verify(&pending.secret, code, pending.last_used_step)
+        .map_err(|e| {
+            log::error!("Failed to verify TOTP code of user {user_id}: {e}");
+            DbError::InternalError
+        })?
+        .ok_or(DbError::WrongCredentials)?;
+
+    let mut pending: user_totp::ActiveModel = pending.into();
+    pending.confirmed_at = Set(Some(Utc::now().naive_utc()));
+    pending.last_used_step = Set(Some(step));
+    pending
+        .update(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    let codes = replace_recovery_codes(&txn, user_id).await?;
+
+    txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+    Ok(codes)
+}
+
+/// User a pending challenge was issued to
+async fn challenge_owner(db: &DatabaseConnection, challenge: &str) -> Result<Option<i32>, DbError> {
+    login_challenge::Entity::find_by_id(tokens::hash(challenge))
+        .one(db)
+        .await
+        .map(|challenge| challenge.map(|challenge| challenge.user_id))
+        .map_err(|_| DbError::InternalError)
+}
+
+/// Uses up the challenge on success, wrong codes count against it
+async fn answer_challenge(
+    db: &DatabaseConnection,
+    body: &TwoFactorLoginRequest,
+    client: &ClientInfo,
+) -> Result<user::Model, DbError> {
+    let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+
+    let challenge = login_challenge::Entity::find_by_id(tokens::hash(&body.challenge))
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::NotFound)?;
+
+    if challenge.expires_at < Utc::now().naive_utc() {
+        return Err(DbError::NotFound);
+    }
+
+    let user_id = challenge.user_id;
+
+    if check_code(&txn, user_id, &body.code).await? {
+        login_challenge::Entity::delete_by_id(challenge.token_hash)
+            .exec(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+
+        let user = user::Entity::find_by_id(user_id)
+            .one(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?
+            .ok_or(DbError::NotFound)?;
+
+        txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+        return Ok(user);
+    }
+
+    if challenge.attempts + 1 >= CHALLENGE_ATTEMPTS {
+        login_challenge::Entity::delete_by_id(challenge.token_hash)
+            .exec(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+    } else {
+        let attempts = challenge.attempts + 1;
+        let mut challenge: login_challenge::ActiveModel = challenge.into();
+        challenge.attempts = Set(attempts);
+        challenge
+            .update(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+    }
+
+    txn.commit().await.map_err(|_| DbError::InternalError)?;
+
+    let entry = Entry::failure(Event::Login)
+        .target(user_id)
+        .client(client)
+        .detail("wrong second factor");
+    audit::record(db, entry).await;
+
+    Err(DbError::WrongCredentials)
+}
+
+/// Checks a TOTP or recovery code of a user with 2FA enabled, the code is used up
+async fn check_code(txn: &DatabaseTransaction, user_id: i32, code: &str) -> Result<bool, DbError> {
+    let enabled = user_totp::Entity::find_by_id(user_id)
+        .filter(user_totp::Column::ConfirmedAt.is_not_null())
+        .lock_exclusive()
+        .one(txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::NotFound)?;
+
+    let step = totp::verify(&enabled.secret, code, enabled.last_used_step).map_err(|e| {
+        log::error!("Failed to verify TOTP code of user {user_id}: {e}");
+        DbError::InternalError
+    })?;
+
+    if let Some(step) = step {
+        let mut enabled: user_totp::ActiveModel = enabled.into();
+        enabled.last_used_step = Set(Some(step));
+        enabled
+            .update(txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+
+        return Ok(true);
+    }
+
+    let code_hash = tokens::hash(&totp::normalize_recovery_code(code));
+    let used = recovery_code::Entity::update_many()
+        .col_expr(recovery_code::Column::UsedAt, Utc::now().naive_utc().into())
+        .filter(recovery_code::Column::CodeHash.eq(code_hash))
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .filter(recovery_code::Column::UsedAt.is_null())
+        .exec(txn)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    Ok(used.rows_affected == 1)
+}
+
+async fn replace_recovery_codes(
+    txn: &DatabaseTransaction,
+    user_id: i32,
+) -> Result<RecoveryCodes, DbError> {
+    recovery_code::Entity::delete_many()
+        .filter(recovery_code::Column::UserId.eq(user_id))
+        .exec(txn)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    let codes = totp::generate_recovery_codes();
+
+    recovery_code::Entity::insert_many(codes.iter().map(|code| recovery_code::ActiveModel {
+        code_hash: Set(tokens::hash(&totp::normalize_recovery_code(code))),
+        user_id: Set(user_id),
+        ..Default::default()
+    }))
+    .exec(txn)
+    .await
+    .map_err(|_| DbError::FailedToAdd)?;
+
+    Ok(RecoveryCodes {
+        recovery_codes: codes,
+    })
+}
+
+fn error_response(e: DbError) -> warp::reply::Response {
+    let code = match e {
+        DbError::WrongCredentials => StatusCode::UNAUTHORIZED,
+        DbError::NotFound => StatusCode::NOT_FOUND,
+        DbError::AlreadyExists => StatusCode::CONFLICT,
+        DbError::AccountSanctioned => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    warp::reply::with_status(warp::reply::json(&e), code).into_response()
+}
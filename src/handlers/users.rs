@@ -3,13 +3,22 @@ use std::{convert::Infallible, sync::Arc};
 use entity::{follower, user};
 
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbBackend, EntityTrait, ModelTrait, Set,
-    Statement, TransactionTrait, QueryFilter,
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbBackend, EntityTrait, ModelTrait,
+    QueryFilter, Set, Statement, TransactionTrait,
 };
 use tokio::sync::Mutex;
 use warp::{hyper::StatusCode, Reply};
 
-use crate::{errors::db::DbError, models::user::FollowersOfUser};
+use crate::{
+    errors::{db::DbError, error_reply, validation::FieldError},
+    models::user::{FollowersOfUser, Profile},
+    validation::TAKEN,
+};
+
+/// Usernames and emails are stored and looked up trimmed and in lowercase
+pub fn normalize_identifier(value: &str) -> String {
+    value.trim().to_lowercase()
+}
 
 pub async fn list(
     db_session: Arc<Mutex<DatabaseConnection>>,
@@ -18,6 +27,7 @@ pub async fn list(
     // Just return a JSON array of users
     let db = db_session.lock().await.to_owned();
     let users: Vec<user::Model> = user::Entity::find().all(&db).await.unwrap();
+    let users: Vec<Profile> = users.into_iter().map(Profile::from).collect();
 
     Ok(warp::reply::json(&users))
 }
@@ -49,7 +59,7 @@ pub async fn get_by_id(
         .into_response());
     }
 
-    Ok(warp::reply::json(&user.unwrap()).into_response())
+    Ok(warp::reply::json(&Profile::from(user.unwrap())).into_response())
 }
 
 pub async fn get_by_username(
@@ -60,7 +70,7 @@ pub async fn get_by_username(
     // Just return a JSON object of user
     let db = db_session.lock().await.to_owned();
     let user = user::Entity::find()
-        .filter(user::Column::Username.eq(username))
+        .filter(user::Column::Username.eq(normalize_identifier(&username)))
         .one(&db)
         .await;
 
@@ -82,29 +92,67 @@ pub async fn get_by_username(
         .into_response());
     }
 
-    Ok(warp::reply::json(&user.unwrap()).into_response())
+    Ok(warp::reply::json(&Profile::from(user.unwrap())).into_response())
 }
 
+/// Answers 409 naming the field when the username or email is taken
 pub async fn create(
     user: user::ActiveModel,
     db_session: Arc<Mutex<DatabaseConnection>>,
-) -> Result<StatusCode, Infallible> {
+) -> Result<warp::reply::Response, Infallible> {
     let db = db_session.lock().await.to_owned();
     log::debug!("create_user: {:?}", user);
 
-    if check_user_by_username(db_session.clone(), &user.username.clone().unwrap())
-        .await
-        .is_err()
-    {
-        return Ok(StatusCode::BAD_REQUEST);
+    let username = user.username.clone().unwrap();
+    let email = user.email.clone().unwrap();
+
+    match taken_field(db_session.clone(), &username, &email).await {
+        Ok(None) => (),
+        Ok(Some(field)) => return Ok(conflict(field)),
+        Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
     }
 
     match user.insert(&db).await {
-        Ok(_) => Ok(StatusCode::CREATED),
-        Err(_e) => Ok(StatusCode::INTERNAL_SERVER_ERROR),
+        Ok(_) => Ok(StatusCode::CREATED.into_response()),
+        // the unique indexes catch registrations racing for the same name or address
+        Err(_e) => match taken_field(db_session, &username, &email).await {
+            Ok(Some(field)) => Ok(conflict(field)),
+            _ => Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+        },
+    }
+}
+
+async fn taken_field(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    username: &str,
+    email: &str,
+) -> Result<Option<&'static str>, DbError> {
+    match check_user_by_username(db_session.clone(), username).await {
+        Err(DbError::AlreadyExists) => return Ok(Some("username")),
+        Err(e) => return Err(e),
+        Ok(_) => (),
+    }
+
+    match check_user_by_email(db_session, email).await {
+        Err(DbError::AlreadyExists) => Ok(Some("email")),
+        Err(e) => Err(e),
+        Ok(_) => Ok(None),
     }
 }
 
+/// Same body as a failed validation, so clients read every field error alike
+fn conflict(field: &'static str) -> warp::reply::Response {
+    error_reply(
+        StatusCode::CONFLICT,
+        "Already taken".to_string(),
+        vec![FieldError {
+            field,
+            code: TAKEN,
+            message: "Is already taken".to_string(),
+        }],
+    )
+}
+
 // pub async fn update(
 //     id: String,
 //     _id_from_token: i32,
@@ -168,7 +216,30 @@ pub async fn check_user_by_username(
 ) -> Result<(), DbError> {
     let db = db_session.lock().await.to_owned();
     let user = user::Entity::find()
-        .filter(user::Column::Username.eq(username))
+        .filter(user::Column::Username.eq(normalize_identifier(username)))
+        .one(&db)
+        .await;
+
+    if user.is_err() {
+        return Err(DbError::InternalError);
+    }
+
+    let user = user.unwrap();
+
+    if user.is_some() {
+        return Err(DbError::AlreadyExists);
+    }
+
+    Ok(())
+}
+
+pub async fn check_user_by_email(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    email: &str,
+) -> Result<(), DbError> {
+    let db = db_session.lock().await.to_owned();
+    let user = user::Entity::find()
+        .filter(user::Column::Email.eq(normalize_identifier(email)))
         .one(&db)
         .await;
 
@@ -0,0 +1,187 @@
+use std::{convert::Infallible, sync::Arc};
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set,
+    TransactionTrait,
+};
+use tokio::sync::Mutex;
+use warp::{hyper::StatusCode, Reply};
+
+use entity::{email_verification, user};
+
+use crate::{
+    errors::db::DbError,
+    mailer::{self, Mail},
+    requests::auth::{ResendVerificationRequest, VerifyQuery},
+    tokens,
+};
+
+use super::users::normalize_identifier;
+
+/// How long a verification link stays valid, in seconds
+const VERIFICATION_LIFETIME: i64 = 60 * 60 * 24;
+
+pub async fn verify(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    query: VerifyQuery,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    match verify_email(&db, &query.token).await {
+        Ok(_) => Ok(StatusCode::OK.into_response()),
+        Err(DbError::NotFound) => Ok(warp::reply::with_status(
+            warp::reply::json(&DbError::NotFound),
+            StatusCode::BAD_REQUEST,
+        )
+        .into_response()),
+        Err(DbError::AlreadyExists) => Ok(warp::reply::with_status(
+            warp::reply::json(&DbError::AlreadyExists),
+            StatusCode::CONFLICT,
+        )
+        .into_response()),
+        Err(e) => Ok(warp::reply::with_status(
+            warp::reply::json(&e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .into_response()),
+    }
+}
+
+/// Always accepted, so the response does not tell which addresses are registered
+pub async fn resend(
+    db_session: Arc<Mutex<DatabaseConnection>>,
+    body: ResendVerificationRequest,
+) -> Result<warp::reply::Response, Infallible> {
+    let db = db_session.lock().await.to_owned();
+
+    let user = user::Entity::find()
+        .filter(user::Column::Email.eq(normalize_identifier(&body.email)))
+        .filter(user::Column::EmailVerifiedAt.is_null())
+        .one(&db)
+        .await;
+
+    match user {
+        Ok(Some(user)) => {
+            if let Err(e) = send_verification(&db, &user).await {
+                log::error!("Failed to resend verification to user {}: {e:?}", user.id);
+            }
+        }
+        Ok(None) => (),
+        Err(e) => log::error!("Failed to look up user for verification: {e}"),
+    }
+
+    Ok(StatusCode::ACCEPTED.into_response())
+}
+
+/// Stores a single-use token and mails the verification link to the user
+///
+/// The mail is delivered in the background
+pub async fn send_verification(db: &DatabaseConnection, user: &user::Model) -> Result<(), DbError> {
+    store_and_mail(db, user, None).await
+}
+
+/// Mails the verification link to the new address, the address of the user
+/// is only changed once the link is opened
+pub async fn send_email_change(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    email: String,
+) -> Result<(), DbError> {
+    store_and_mail(db, user, Some(email)).await
+}
+
+async fn store_and_mail(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    new_email: Option<String>,
+) -> Result<(), DbError> {
+    let token = tokens::generate();
+    let expires_at = Utc::now().naive_utc() + chrono::Duration::seconds(VERIFICATION_LIFETIME);
+
+    email_verification::ActiveModel {
+        token_hash: Set(tokens::hash(&token)),
+        user_id: Set(user.id),
+        expires_at: Set(expires_at),
+        email: Set(new_email.clone()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|_| DbError::FailedToAdd)?;
+
+    let mail = Mail {
+        to: new_email.unwrap_or_else(|| user.email.clone()),
+        subject: "Verify your Nova account".to_owned(),
+        body: format!(
+            "Hi {},\n\nconfirm your email address by opening the link below:\n\n{}/api/auth/verify?token={}\n\nThe link expires in 24 hours.\n",
+            user.username,
+            mailer::public_url(),
+            token
+        ),
+    };
+
+    tokio::spawn(async move {
+        if let Err(e) = mailer::mailer().send(mail).await {
+            log::error!("Failed to send verification mail: {e}");
+        }
+    });
+
+    Ok(())
+}
+
+/// Marks the address as verified, or switches to the new address of an email change,
+/// and uses up every pending token of the user
+async fn verify_email(db: &DatabaseConnection, token: &str) -> Result<(), DbError> {
+    let txn = db.begin().await.map_err(|_| DbError::InternalError)?;
+
+    let verification = email_verification::Entity::find_by_id(tokens::hash(token))
+        .one(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::NotFound)?;
+
+    if verification.expires_at < Utc::now().naive_utc() {
+        return Err(DbError::NotFound);
+    }
+
+    let user = user::Entity::find_by_id(verification.user_id)
+        .one(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?
+        .ok_or(DbError::NotFound)?;
+
+    email_verification::Entity::delete_many()
+        .filter(email_verification::Column::UserId.eq(user.id))
+        .exec(&txn)
+        .await
+        .map_err(|_| DbError::InternalError)?;
+
+    if let Some(email) = verification.email {
+        // the address could have been taken since the change was requested
+        let taken = user::Entity::find()
+            .filter(user::Column::Email.eq(&email))
+            .filter(user::Column::Id.ne(user.id))
+            .one(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+        if taken.is_some() {
+            return Err(DbError::AlreadyExists);
+        }
+
+        let mut user: user::ActiveModel = user.into();
+        user.email = Set(email);
+        user.email_verified_at = Set(Some(Utc::now().naive_utc()));
+        user.update(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+    } else if user.email_verified_at.is_none() {
+        let mut user: user::ActiveModel = user.into();
+        user.email_verified_at = Set(Some(Utc::now().naive_utc()));
+        user.update(&txn)
+            .await
+            .map_err(|_| DbError::InternalError)?;
+    }
+
+    txn.commit().await.map_err(|_| DbError::InternalError)
+}
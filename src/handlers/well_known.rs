@@ -0,0 +1,8 @@
+use std::convert::Infallible;
+
+use crate::jwt::keys;
+
+/// Public signing keys as a JWK Set
+pub async fn jwks() -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&keys::jwks()))
+}
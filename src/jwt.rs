@@ -1,40 +1,70 @@
 use chrono::Utc;
-use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use jsonwebtoken::{encode, Header};
 use serde::{Deserialize, Serialize};
 use warp::{
     http::HeaderValue,
     hyper::{header::AUTHORIZATION, HeaderMap},
 };
 
-use crate::{errors::jwt::JWTError, models::role::Role};
+use crate::{
+    cookies,
+    errors::jwt::JWTError,
+    models::{permission::Permission, role::RoleInfo},
+};
+
+pub mod keys;
 
 const BEARER: &str = "Bearer ";
 
+/// Lifetime of access tokens in seconds
+pub const TOKEN_LIFETIME: i64 = 60 * 15;
+
+/// Lifetime of refresh tokens in seconds
+pub const REFRESH_TOKEN_LIFETIME: i64 = 60 * 60 * 24 * 30;
+
+/// Where the access token of a request was found
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenSource {
+    Header,
+    /// Sent by the browser on its own, so CSRF has to be ruled out
+    Cookie,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: usize,
+    pub sid: i32,
     pub role: String,
+    /// Id of the role
+    pub rid: i32,
+    /// Version of the role permissions below
+    pub pv: i32,
+    pub perms: Vec<Permission>,
     pub exp: usize,
 }
 
-/// Creates JWT
-pub fn generate_jwt(uid: i32, role: Role) -> Result<String, JWTError> {
-    let seconds = 60 * 60 * 24 * 365;
+/// Creates a short-lived access token bound to the session
+pub fn generate_jwt(uid: i32, sid: i32, role: &RoleInfo) -> Result<String, JWTError> {
     let expiration = Utc::now()
-        .checked_add_signed(chrono::Duration::seconds(seconds))
+        .checked_add_signed(chrono::Duration::seconds(TOKEN_LIFETIME))
         .expect("Invalid timestamp")
         .timestamp();
 
     let claims = Claims {
         sub: uid as usize,
-        role: role.to_string().to_owned(),
+        sid,
+        role: role.name.clone(),
+        rid: role.id,
+        pv: role.permissions_version,
+        perms: role.permissions.clone(),
         exp: expiration as usize,
     };
 
-    let header = Header::new(Algorithm::HS512);
+    let (kid, algorithm, key) = keys::signing_key()?;
+    let mut header = Header::new(algorithm);
+    header.kid = Some(kid);
 
-    encode(&header, &claims, &EncodingKey::from_secret(b"123"))
-        .map_err(|_| JWTError::JWTTokenCreationError)
+    encode(&header, &claims, &key).map_err(|_| JWTError::JWTTokenCreationError)
 }
 
 /// Extracts JWT from Header
@@ -55,3 +85,16 @@ pub fn jwt_from_header(headers: &HeaderMap<HeaderValue>) -> Result<String, JWTEr
 
     Ok(auth_header.trim_start_matches(BEARER).to_owned())
 }
+
+/// Extracts JWT from Header, or from the session cookie when there is no header
+pub fn jwt_from_request(
+    headers: &HeaderMap<HeaderValue>,
+) -> Result<(String, TokenSource), JWTError> {
+    match jwt_from_header(headers) {
+        Ok(token) => Ok((token, TokenSource::Header)),
+        Err(JWTError::NoAuthHeaderError) => cookies::read(headers, cookies::ACCESS_COOKIE)
+            .map(|token| (token, TokenSource::Cookie))
+            .ok_or(JWTError::NoAuthHeaderError),
+        Err(e) => Err(e),
+    }
+}
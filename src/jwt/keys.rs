@@ -0,0 +1,543 @@
+use std::{
+    collections::HashMap,
+    io::BufReader,
+    str::FromStr,
+    sync::{Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use chrono::{NaiveDateTime, Utc};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, OctetKeyPairParameters,
+        OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use rand::RngCore;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+
+use entity::jwt_key;
+
+use crate::errors::jwt::JWTError;
+
+use super::TOKEN_LIFETIME;
+
+/// How often every instance re-reads the stored keys
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60 * 5);
+
+/// Shortest time between two reloads caused by tokens with an unknown `kid`
+const MISS_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Length of generated HS512 secrets in bytes
+const SECRET_LENGTH: usize = 64;
+
+/// Secret of the `jwt_key` rows recording that a configured key was retired,
+/// its material stays in the configuration
+const RETIRED_CONFIG_KEY: &str = "";
+
+static KEY_RING: OnceLock<RwLock<KeyRing>> = OnceLock::new();
+
+/// When an unknown `kid` last caused a reload
+static LAST_MISS_RELOAD: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Key material for each supported signing mode
+enum KeyMaterial {
+    /// HS512 shared secret
+    Hmac(Vec<u8>),
+    /// EdDSA key, PKCS#8 private key and raw public key
+    Ed25519 { private: Vec<u8>, public: Vec<u8> },
+    /// RS256 key, PKCS#1 private key and public components
+    Rsa {
+        private: Vec<u8>,
+        modulus: Vec<u8>,
+        exponent: Vec<u8>,
+    },
+}
+
+impl KeyMaterial {
+    /// Parses DER encoded private keys, secrets are taken as is
+    fn from_der(algorithm: Algorithm, der: Vec<u8>) -> Result<KeyMaterial, String> {
+        match algorithm {
+            Algorithm::HS512 => Ok(KeyMaterial::Hmac(der)),
+            Algorithm::EdDSA => {
+                let pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der)
+                    .map_err(|e| format!("Invalid Ed25519 key: {e}"))?;
+                let public = pair.public_key().as_ref().to_vec();
+
+                Ok(KeyMaterial::Ed25519 {
+                    private: der,
+                    public,
+                })
+            }
+            Algorithm::RS256 => {
+                let pair =
+                    RsaKeyPair::from_der(&der).map_err(|e| format!("Invalid RSA key: {e}"))?;
+                let modulus = pair
+                    .public_key()
+                    .modulus()
+                    .big_endian_without_leading_zero()
+                    .to_vec();
+                let exponent = pair
+                    .public_key()
+                    .exponent()
+                    .big_endian_without_leading_zero()
+                    .to_vec();
+
+                Ok(KeyMaterial::Rsa {
+                    private: der,
+                    modulus,
+                    exponent,
+                })
+            }
+            _ => Err(format!("Unsupported JWT algorithm {algorithm:?}")),
+        }
+    }
+
+    /// Reads a PEM file, PKCS#8 for Ed25519 and PKCS#1 for RSA
+    fn from_pem_file(algorithm: Algorithm, path: &str) -> Result<KeyMaterial, String> {
+        let file = std::fs::File::open(path).map_err(|e| format!("Failed to open {path}: {e}"))?;
+
+        let der = match rustls_pemfile::read_one(&mut BufReader::new(file)) {
+            Ok(Some(rustls_pemfile::Item::PKCS8Key(der))) if algorithm == Algorithm::EdDSA => der,
+            Ok(Some(rustls_pemfile::Item::RSAKey(der))) if algorithm == Algorithm::RS256 => der,
+            _ => {
+                return Err(format!(
+                    "{path} does not contain a {algorithm:?} private key"
+                ))
+            }
+        };
+
+        KeyMaterial::from_der(algorithm, der)
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        match self {
+            KeyMaterial::Hmac(_) => Algorithm::HS512,
+            KeyMaterial::Ed25519 { .. } => Algorithm::EdDSA,
+            KeyMaterial::Rsa { .. } => Algorithm::RS256,
+        }
+    }
+
+    /// Private part in the form it is stored in the DB
+    fn to_der(&self) -> &[u8] {
+        match self {
+            KeyMaterial::Hmac(secret) => secret,
+            KeyMaterial::Ed25519 { private, .. } => private,
+            KeyMaterial::Rsa { private, .. } => private,
+        }
+    }
+}
+
+/// Signing key, identified in tokens by the `kid` header
+pub struct SigningKey {
+    pub kid: String,
+    material: KeyMaterial,
+    pub retired_at: Option<NaiveDateTime>,
+    /// Comes from `JWT_KEYS` rather than from rotation
+    configured: bool,
+}
+
+impl SigningKey {
+    /// Retired keys still verify until the last token they signed expires
+    fn is_usable(&self) -> bool {
+        match self.retired_at {
+            Some(retired_at) => {
+                retired_at + chrono::Duration::seconds(TOKEN_LIFETIME) > Utc::now().naive_utc()
+            }
+            None => true,
+        }
+    }
+
+    fn encoding_key(&self) -> EncodingKey {
+        match &self.material {
+            KeyMaterial::Hmac(secret) => EncodingKey::from_secret(secret),
+            KeyMaterial::Ed25519 { private, .. } => EncodingKey::from_ed_der(private),
+            KeyMaterial::Rsa { private, .. } => EncodingKey::from_rsa_der(private),
+        }
+    }
+
+    fn decoding_key(&self) -> DecodingKey {
+        match &self.material {
+            KeyMaterial::Hmac(secret) => DecodingKey::from_secret(secret),
+            KeyMaterial::Ed25519 { public, .. } => DecodingKey::from_ed_der(public),
+            KeyMaterial::Rsa {
+                modulus, exponent, ..
+            } => DecodingKey::from_rsa_raw_components(modulus, exponent),
+        }
+    }
+
+    /// Public key for the JWKS, shared secrets are never published
+    fn jwk(&self) -> Option<Jwk> {
+        let algorithm = match &self.material {
+            KeyMaterial::Hmac(_) => return None,
+            KeyMaterial::Ed25519 { public, .. } => {
+                AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(public),
+                })
+            }
+            KeyMaterial::Rsa {
+                modulus, exponent, ..
+            } => AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(modulus),
+                e: URL_SAFE_NO_PAD.encode(exponent),
+            }),
+        };
+
+        Some(Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                algorithm: Some(self.material.algorithm()),
+                key_id: Some(self.kid.clone()),
+                ..Default::default()
+            },
+            algorithm,
+        })
+    }
+}
+
+/// All keys known to the server, only the active one signs new tokens
+pub struct KeyRing {
+    algorithm: Algorithm,
+    active: Option<String>,
+    keys: HashMap<String, SigningKey>,
+}
+
+impl KeyRing {
+    /// Reads the signing mode from `JWT_ALGORITHM` (`HS512`, `EdDSA` or `RS256`),
+    /// keys from `JWT_KEYS` and the signing key from `JWT_ACTIVE_KID`
+    ///
+    /// `JWT_KEYS` holds `kid:secret` pairs separated by commas in the HS512 mode,
+    /// and `kid:path` pairs pointing to PEM private keys in the asymmetric ones
+    fn from_config() -> Result<KeyRing, String> {
+        let algorithm = match std::env::var("JWT_ALGORITHM") {
+            Ok(algorithm) => Algorithm::from_str(&algorithm)
+                .map_err(|_| format!("Unknown JWT_ALGORITHM `{algorithm}`"))?,
+            Err(_) => Algorithm::HS512,
+        };
+        if ![Algorithm::HS512, Algorithm::EdDSA, Algorithm::RS256].contains(&algorithm) {
+            return Err(format!("Unsupported JWT_ALGORITHM {algorithm:?}"));
+        }
+
+        let mut ring = KeyRing {
+            algorithm,
+            active: None,
+            keys: HashMap::new(),
+        };
+
+        let keys = std::env::var("JWT_KEYS").map_err(|_| "JWT_KEYS is not set".to_owned())?;
+        for pair in keys.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (kid, value) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("Expected `kid:value` in JWT_KEYS, got `{pair}`"))?;
+
+            let material = match algorithm {
+                Algorithm::HS512 => KeyMaterial::Hmac(value.as_bytes().to_vec()),
+                _ => KeyMaterial::from_pem_file(algorithm, value)?,
+            };
+
+            ring.insert(SigningKey {
+                kid: kid.to_owned(),
+                material,
+                retired_at: None,
+                configured: true,
+            });
+        }
+
+        let active =
+            std::env::var("JWT_ACTIVE_KID").map_err(|_| "JWT_ACTIVE_KID is not set".to_owned())?;
+        if !ring.keys.contains_key(&active) {
+            return Err(format!("JWT_ACTIVE_KID `{active}` is not in JWT_KEYS"));
+        }
+        ring.active = Some(active);
+
+        Ok(ring)
+    }
+
+    /// Adds keys created by rotation, the newest live one of
+    /// the configured algorithm takes over signing
+    ///
+    /// Configured keys retired by a rotation are dropped once their grace period passed
+    fn merge_stored(&mut self, stored: Vec<jwt_key::Model>) {
+        for key in stored {
+            if key.secret == RETIRED_CONFIG_KEY {
+                if let Some(configured) = self.keys.get_mut(&key.kid).filter(|k| k.configured) {
+                    configured.retired_at = key.retired_at;
+                    if self.active.as_ref() == Some(&key.kid) {
+                        self.active = None;
+                    }
+                }
+                continue;
+            }
+
+            let material = Algorithm::from_str(&key.algorithm)
+                .map_err(|e| e.to_string())
+                .and_then(|algorithm| {
+                    let der = STANDARD.decode(&key.secret).map_err(|e| e.to_string())?;
+                    KeyMaterial::from_der(algorithm, der)
+                });
+            let material = match material {
+                Ok(material) => material,
+                Err(e) => {
+                    log::error!("Stored JWT key `{}` is invalid: {e}", key.kid);
+                    continue;
+                }
+            };
+
+            if key.retired_at.is_none() && material.algorithm() == self.algorithm {
+                self.active = Some(key.kid.clone());
+            }
+
+            self.insert(SigningKey {
+                kid: key.kid,
+                material,
+                retired_at: key.retired_at,
+                configured: false,
+            });
+        }
+
+        self.keys.retain(|_, key| key.is_usable());
+    }
+
+    fn insert(&mut self, key: SigningKey) {
+        self.keys.insert(key.kid.clone(), key);
+    }
+}
+
+fn ring() -> &'static RwLock<KeyRing> {
+    KEY_RING.get_or_init(|| {
+        RwLock::new(KeyRing {
+            algorithm: Algorithm::HS512,
+            active: None,
+            keys: HashMap::new(),
+        })
+    })
+}
+
+/// Builds the key ring from the configuration and the rotated keys in the DB
+pub async fn load(db: &DatabaseConnection) -> Result<(), String> {
+    let mut key_ring = KeyRing::from_config()?;
+
+    let stored = jwt_key::Entity::find()
+        .order_by_asc(jwt_key::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+    key_ring.merge_stored(stored);
+
+    *ring().write().unwrap() = key_ring;
+
+    Ok(())
+}
+
+/// Periodically reloads the keys, so rotations done by other instances are picked up
+pub fn watch(db: DatabaseConnection) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        // the first tick completes immediately
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = load(&db).await {
+                log::error!("Failed to reload JWT keys: {e}");
+            }
+        }
+    });
+}
+
+/// Key that signs new tokens
+pub fn signing_key() -> Result<(String, Algorithm, EncodingKey), JWTError> {
+    let key_ring = ring().read().unwrap();
+
+    let key = key_ring
+        .active
+        .as_ref()
+        .and_then(|kid| key_ring.keys.get(kid))
+        .ok_or(JWTError::JWTTokenCreationError)?;
+
+    Ok((
+        key.kid.clone(),
+        key.material.algorithm(),
+        key.encoding_key(),
+    ))
+}
+
+/// Key that verifies a token with the given `kid`
+///
+/// Unknown ids may trigger a reload, as the key might have been rotated by another instance.
+/// The `kid` comes from unauthenticated tokens, so such reloads happen at most once
+/// per `MISS_RELOAD_INTERVAL`, otherwise rotations are picked up by `watch`
+pub async fn decoding_key(
+    db: &DatabaseConnection,
+    kid: &str,
+) -> Result<(Algorithm, DecodingKey), JWTError> {
+    if let Some(key) = find_decoding_key(kid) {
+        return Ok(key);
+    }
+
+    if !claim_miss_reload() {
+        return Err(JWTError::JWTTokenError);
+    }
+
+    load(db).await.map_err(|e| {
+        log::error!("Failed to reload JWT keys: {e}");
+        JWTError::JWTTokenError
+    })?;
+
+    find_decoding_key(kid).ok_or(JWTError::JWTTokenError)
+}
+
+/// Whether a reload for an unknown `kid` is allowed now, records it if so
+fn claim_miss_reload() -> bool {
+    let mut last = LAST_MISS_RELOAD.lock().unwrap();
+    let now = Instant::now();
+
+    match *last {
+        Some(at) if now.duration_since(at) < MISS_RELOAD_INTERVAL => false,
+        _ => {
+            *last = Some(now);
+            true
+        }
+    }
+}
+
+fn find_decoding_key(kid: &str) -> Option<(Algorithm, DecodingKey)> {
+    let key_ring = ring().read().unwrap();
+
+    key_ring
+        .keys
+        .get(kid)
+        .filter(|key| key.is_usable())
+        .map(|key| (key.material.algorithm(), key.decoding_key()))
+}
+
+/// Public keys of the asymmetric modes, for services verifying tokens offline
+pub fn jwks() -> JwkSet {
+    let key_ring = ring().read().unwrap();
+
+    JwkSet {
+        keys: key_ring
+            .keys
+            .values()
+            .filter(|key| key.is_usable())
+            .filter_map(SigningKey::jwk)
+            .collect(),
+    }
+}
+
+/// Generates a new signing key for the configured algorithm and retires the current ones,
+/// including the keys from `JWT_KEYS`
+///
+/// Tokens signed by retired keys stay valid until they expire.
+/// RSA keys can not be generated, they are rotated through `JWT_KEYS`
+pub async fn rotate(db: &DatabaseConnection) -> Result<String, JWTError> {
+    let (algorithm, configured) = {
+        let key_ring = ring().read().unwrap();
+        let configured: Vec<String> = key_ring
+            .keys
+            .values()
+            .filter(|key| key.configured && key.retired_at.is_none())
+            .map(|key| key.kid.clone())
+            .collect();
+
+        (key_ring.algorithm, configured)
+    };
+
+    let material = match algorithm {
+        Algorithm::HS512 => {
+            let mut secret = vec![0u8; SECRET_LENGTH];
+            rand::thread_rng().fill_bytes(&mut secret);
+            KeyMaterial::Hmac(secret)
+        }
+        Algorithm::EdDSA => {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
+                .map_err(|_| JWTError::KeyRotationError)?;
+            KeyMaterial::from_der(algorithm, pkcs8.as_ref().to_vec())
+                .map_err(|_| JWTError::KeyRotationError)?
+        }
+        _ => return Err(JWTError::KeyRotationUnsupportedError),
+    };
+
+    let now = Utc::now().naive_utc();
+    let kid = now.format("%Y%m%d%H%M%S%3f").to_string();
+
+    store(db, &kid, &material, &configured, now)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to store JWT key: {e}");
+            JWTError::KeyRotationError
+        })?;
+
+    load(db).await.map_err(|e| {
+        log::error!("Failed to reload JWT keys: {e}");
+        JWTError::KeyRotationError
+    })?;
+
+    Ok(kid)
+}
+
+async fn store(
+    db: &DatabaseConnection,
+    kid: &str,
+    material: &KeyMaterial,
+    configured: &[String],
+    now: NaiveDateTime,
+) -> Result<(), sea_orm::DbErr> {
+    let txn = db.begin().await?;
+
+    jwt_key::Entity::update_many()
+        .col_expr(jwt_key::Column::RetiredAt, now.into())
+        .filter(jwt_key::Column::RetiredAt.is_null())
+        .exec(&txn)
+        .await?;
+
+    // dropping keys, which can not verify any token anymore,
+    // configured keys are kept retired, as they would be back otherwise
+    jwt_key::Entity::delete_many()
+        .filter(jwt_key::Column::RetiredAt.lt(now - chrono::Duration::seconds(TOKEN_LIFETIME)))
+        .filter(jwt_key::Column::Secret.ne(RETIRED_CONFIG_KEY))
+        .exec(&txn)
+        .await?;
+
+    for kid in configured {
+        // another instance may have retired it already
+        jwt_key::Entity::insert(jwt_key::ActiveModel {
+            kid: Set(kid.to_owned()),
+            secret: Set(RETIRED_CONFIG_KEY.to_owned()),
+            algorithm: Set(format!("{:?}", material.algorithm())),
+            created_at: Set(now),
+            retired_at: Set(Some(now)),
+        })
+        .on_conflict(
+            OnConflict::column(jwt_key::Column::Kid)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+    }
+
+    jwt_key::ActiveModel {
+        kid: Set(kid.to_owned()),
+        secret: Set(STANDARD.encode(material.to_der())),
+        algorithm: Set(format!("{:?}", material.algorithm())),
+        created_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    txn.commit().await
+}
@@ -0,0 +1,63 @@
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+use lettre::{message::header::ContentType, Message};
+
+use crate::errors::mail::MailError;
+
+use self::{outbox::OutboxMailer, smtp::SmtpMailer};
+
+pub mod outbox;
+pub mod smtp;
+
+static MAILER: OnceLock<Box<dyn Mailer>> = OnceLock::new();
+
+/// Plain text mail to a single recipient
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Mail delivery backend
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, mail: Mail) -> Result<(), MailError>;
+}
+
+/// Selects the backend with `MAILER`: `smtp` or `outbox` (default)
+pub fn init() -> Result<(), String> {
+    let mailer: Box<dyn Mailer> = match std::env::var("MAILER").as_deref() {
+        Ok("smtp") => Box::new(SmtpMailer::from_env()?),
+        Ok("outbox") | Err(_) => Box::new(OutboxMailer::from_env()?),
+        Ok(other) => return Err(format!("Unknown MAILER `{other}`")),
+    };
+
+    MAILER
+        .set(mailer)
+        .map_err(|_| "Mailer is already initialized".to_owned())
+}
+
+pub fn mailer() -> &'static dyn Mailer {
+    MAILER.get().expect("Mailer is not initialized").as_ref()
+}
+
+/// Public address of the server, used for links in mails
+pub fn public_url() -> String {
+    std::env::var("PUBLIC_URL").unwrap_or_else(|_| "https://localhost:8085".to_owned())
+}
+
+/// Builds the message sent by every backend, the sender is taken from `MAIL_FROM`
+fn message(mail: Mail) -> Result<Message, MailError> {
+    let from =
+        std::env::var("MAIL_FROM").unwrap_or_else(|_| "Nova <no-reply@localhost>".to_owned());
+
+    Message::builder()
+        .from(from.parse().map_err(|_| MailError::Address(from))?)
+        .to(mail.to.parse().map_err(|_| MailError::Address(mail.to))?)
+        .subject(mail.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(mail.body)
+        .map_err(|_| MailError::Build)
+}
@@ -0,0 +1,35 @@
+use async_trait::async_trait;
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+
+use crate::errors::mail::MailError;
+
+use super::{message, Mail, Mailer};
+
+/// Drops every mail as an `.eml` file into a directory,
+/// so mails can be read locally without a mail server
+pub struct OutboxMailer {
+    transport: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl OutboxMailer {
+    /// Writes to `MAIL_OUTBOX_DIR`, `./outbox` by default
+    pub fn from_env() -> Result<OutboxMailer, String> {
+        let dir = std::env::var("MAIL_OUTBOX_DIR").unwrap_or_else(|_| "./outbox".to_owned());
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {dir}: {e}"))?;
+
+        Ok(OutboxMailer {
+            transport: AsyncFileTransport::new(dir),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for OutboxMailer {
+    async fn send(&self, mail: Mail) -> Result<(), MailError> {
+        self.transport
+            .send(message(mail)?)
+            .await
+            .map(|_| ())
+            .map_err(|e| MailError::Delivery(e.to_string()))
+    }
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use lettre::{
+    transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport,
+    Tokio1Executor,
+};
+
+use crate::errors::mail::MailError;
+
+use super::{message, Mail, Mailer};
+
+/// Delivers mails through an SMTP relay with STARTTLS
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    /// Configured with `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME` and `SMTP_PASSWORD`
+    pub fn from_env() -> Result<SmtpMailer, String> {
+        let host = std::env::var("SMTP_HOST").map_err(|_| "SMTP_HOST is not set".to_owned())?;
+        let port = match std::env::var("SMTP_PORT") {
+            Ok(port) => port
+                .parse()
+                .map_err(|_| format!("Invalid SMTP_PORT `{port}`"))?,
+            Err(_) => 587,
+        };
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+            .map_err(|e| e.to_string())?
+            .port(port);
+
+        if let (Ok(username), Ok(password)) = (
+            std::env::var("SMTP_USERNAME"),
+            std::env::var("SMTP_PASSWORD"),
+        ) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(SmtpMailer {
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: Mail) -> Result<(), MailError> {
+        self.transport
+            .send(message(mail)?)
+            .await
+            .map(|_| ())
+            .map_err(|e| MailError::Delivery(e.to_string()))
+    }
+}
@@ -9,14 +9,27 @@ use tokio::sync::Mutex;
 
 use crate::routes::get_routes;
 
+mod audit;
+mod cookies;
 mod db;
 mod errors;
+mod exports;
 mod filters;
 mod handlers;
 mod jwt;
+mod mailer;
+mod media;
 mod models;
+mod password;
+mod permissions;
 mod requests;
 mod routes;
+mod storage;
+mod throttle;
+mod tokens;
+mod tombstones;
+mod totp;
+mod validation;
 
 #[tokio::main]
 async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
@@ -36,6 +49,44 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     }
     log::info!("DB set up");
 
+    // JWT keys
+    if let Err(e) = jwt::keys::load(&db).await {
+        panic!("Failed to load JWT keys: {e}");
+    }
+    jwt::keys::watch(db.clone());
+
+    // Roles and their permissions
+    if let Err(e) = permissions::load(&db).await {
+        panic!("Failed to load roles: {e}");
+    }
+    permissions::watch(db.clone());
+
+    // Mail delivery
+    if let Err(e) = mailer::init() {
+        panic!("Failed to set up the mailer: {e}");
+    }
+
+    // Personal data exports
+    if let Err(e) = exports::init(&db).await {
+        panic!("Failed to set up exports: {e}");
+    }
+    exports::watch(db.clone());
+
+    // Brute-force protection
+    if let Err(e) = throttle::init(&db) {
+        panic!("Failed to set up throttling: {e}");
+    }
+    throttle::watch();
+
+    // Deleted posts
+    tombstones::watch(db.clone());
+
+    // Media uploads
+    if let Err(e) = storage::init().await {
+        panic!("Failed to set up media storage: {e}");
+    }
+    media::watch(db.clone());
+
     // HTTP server
     let db_session: Arc<Mutex<DatabaseConnection>> = Arc::new(Mutex::new(db));
     let routes = get_routes(db_session);
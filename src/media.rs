@@ -0,0 +1,235 @@
+use std::{io::Cursor, sync::OnceLock, time::Duration};
+
+use bytes::Bytes;
+use chrono::Utc;
+use image::{
+    codecs::{gif::GifDecoder, jpeg::JpegEncoder, png::PngDecoder, webp::WebPDecoder},
+    AnimationDecoder, DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits,
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use tokio::sync::Semaphore;
+
+use entity::media;
+
+use crate::{errors::media::MediaError, storage::storage, tokens};
+
+/// Largest accepted upload by default, in bytes
+const MAX_SIZE: usize = 5 * 1024 * 1024;
+
+/// Longest accepted side of an image, keeps decompression bombs out
+const MAX_DIMENSION: u32 = 8192;
+
+/// Most memory a single decode may allocate, in bytes
+///
+/// Fits a 4096×4096 RGBA image, larger ones are refused even within `MAX_DIMENSION`
+const MAX_ALLOC: u64 = 64 * 1024 * 1024;
+
+/// Images processed at the same time by default, each may hold a few times `MAX_ALLOC`
+const MAX_CONCURRENT: usize = 2;
+
+/// Longest side of thumbnails
+const THUMBNAIL_SIZE: u32 = 320;
+
+const JPEG_QUALITY: u8 = 90;
+
+/// How long an upload waits to be attached to a post, in seconds
+const UNATTACHED_LIFETIME: i64 = 60 * 60 * 24;
+
+/// How often unattached uploads are removed
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+static PROCESSING: OnceLock<Semaphore> = OnceLock::new();
+
+/// Image re-encoded without its metadata, along with its thumbnail
+pub struct Processed {
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub original: Vec<u8>,
+    pub thumbnail: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Largest accepted upload in bytes
+///
+/// Set with `MEDIA_MAX_BYTES`
+pub fn max_size() -> usize {
+    std::env::var("MEDIA_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(MAX_SIZE)
+}
+
+/// Bounds the memory held by decoded images
+///
+/// Set with `MEDIA_MAX_CONCURRENT`
+fn processing() -> &'static Semaphore {
+    PROCESSING.get_or_init(|| {
+        let permits = std::env::var("MEDIA_MAX_CONCURRENT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|permits| *permits > 0)
+            .unwrap_or(MAX_CONCURRENT);
+
+        Semaphore::new(permits)
+    })
+}
+
+/// Decodes the upload and encodes it again, which drops EXIF and any other
+/// metadata, and renders the thumbnail
+///
+/// Runs on the blocking thread pool, so it does not stall the executor,
+/// uploads beyond `MEDIA_MAX_CONCURRENT` wait for their turn
+pub async fn process(data: Bytes) -> Result<Processed, MediaError> {
+    let permit = processing().acquire().await.map_err(|_| MediaError::Task)?;
+
+    let result = tokio::task::spawn_blocking(move || process_blocking(&data))
+        .await
+        .map_err(|_| MediaError::Task)?;
+    drop(permit);
+
+    result
+}
+
+fn process_blocking(data: &[u8]) -> Result<Processed, MediaError> {
+    // the content is trusted over the declared type
+    let format = image::guess_format(data).map_err(|_| MediaError::UnsupportedType)?;
+    if !matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Gif | ImageFormat::WebP
+    ) {
+        return Err(MediaError::UnsupportedType);
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_ALLOC);
+
+    // only the first frame would survive the re-encoding
+    if is_animated(data, format, &limits)? {
+        return Err(MediaError::Animated);
+    }
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().map_err(invalid)?;
+    let orientation = decoder.orientation().map_err(invalid)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(invalid)?;
+    // the EXIF orientation goes with the metadata, so it is applied to the pixels
+    image.apply_orientation(orientation);
+
+    // photos stay JPEG, everything else becomes a lossless PNG
+    let (output, content_type, extension) = match format {
+        ImageFormat::Jpeg => (ImageFormat::Jpeg, "image/jpeg", "jpg"),
+        _ => (ImageFormat::Png, "image/png", "png"),
+    };
+
+    Ok(Processed {
+        content_type,
+        extension,
+        original: encode(&image, output)?,
+        thumbnail: encode(&image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE), output)?,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+fn is_animated(data: &[u8], format: ImageFormat, limits: &Limits) -> Result<bool, MediaError> {
+    match format {
+        ImageFormat::Gif => {
+            let mut decoder = GifDecoder::new(Cursor::new(data)).map_err(invalid)?;
+            decoder.set_limits(limits.clone()).map_err(invalid)?;
+            Ok(decoder.into_frames().take(2).count() > 1)
+        }
+        ImageFormat::WebP => Ok(WebPDecoder::new(Cursor::new(data))
+            .map_err(invalid)?
+            .has_animation()),
+        ImageFormat::Png => PngDecoder::new(Cursor::new(data))
+            .map_err(invalid)?
+            .is_apng()
+            .map_err(invalid),
+        _ => Ok(false),
+    }
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, MediaError> {
+    let mut data = Vec::new();
+
+    match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY)),
+        _ => image.write_to(&mut Cursor::new(&mut data), format),
+    }
+    .map_err(invalid)?;
+
+    Ok(data)
+}
+
+fn invalid(e: image::ImageError) -> MediaError {
+    MediaError::InvalidImage(e.to_string())
+}
+
+/// Random storage keys of an upload of the user, for the original and the thumbnail
+pub fn keys(user_id: i32, extension: &str) -> (String, String) {
+    let name = tokens::generate();
+
+    (
+        format!("media/{user_id}/{name}.{extension}"),
+        format!("media/{user_id}/{name}_thumbnail.{extension}"),
+    )
+}
+
+/// Removes the files of the uploads, failures are only logged
+pub async fn remove_files(uploads: &[media::Model]) {
+    for upload in uploads {
+        remove_keys(&[&upload.original_key, &upload.thumbnail_key]).await;
+    }
+}
+
+pub async fn remove_keys(keys: &[&str]) {
+    for key in keys {
+        if let Err(e) = storage().delete(key).await {
+            log::warn!("Failed to remove media file {key}: {e}");
+        }
+    }
+}
+
+/// Periodically removes uploads which were never attached to a post
+pub fn watch(db: DatabaseConnection) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = purge(&db).await {
+                log::error!("Failed to purge unattached media: {e}");
+            }
+        }
+    });
+}
+
+async fn purge(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let created_before = Utc::now().naive_utc() - chrono::Duration::seconds(UNATTACHED_LIFETIME);
+
+    let unattached = media::Entity::find()
+        .filter(media::Column::PostId.is_null())
+        .filter(media::Column::CreatedAt.lt(created_before))
+        .all(db)
+        .await?;
+
+    for upload in unattached {
+        // attached in the meantime
+        let removed = media::Entity::delete_many()
+            .filter(media::Column::Id.eq(upload.id))
+            .filter(media::Column::PostId.is_null())
+            .exec(db)
+            .await?;
+        if removed.rows_affected > 0 {
+            remove_files(&[upload]).await;
+        }
+    }
+
+    Ok(())
+}
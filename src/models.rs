@@ -1,2 +1,11 @@
+pub mod actor;
+pub mod audit;
+pub mod export;
+pub mod media;
+pub mod permission;
+pub mod personal_token;
+pub mod post;
 pub mod role;
+pub mod session;
+pub mod two_factor;
 pub mod user;
@@ -0,0 +1,14 @@
+use super::permission::Permission;
+
+/// Authenticated caller with the permissions of the token used
+#[derive(Clone, Debug)]
+pub struct Actor {
+    pub id: i32,
+    pub permissions: Vec<Permission>,
+}
+
+impl Actor {
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+}
@@ -0,0 +1,36 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use entity::audit_event;
+
+/// Entry of the audit log
+#[derive(Debug, Serialize)]
+pub struct AuditEventInfo {
+    pub id: i32,
+    pub event: String,
+    pub outcome: String,
+    /// Who performed the action, missing for anonymous requests
+    pub actor_id: Option<i32>,
+    /// Account the action affected
+    pub target_id: Option<i32>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub detail: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<audit_event::Model> for AuditEventInfo {
+    fn from(event: audit_event::Model) -> Self {
+        Self {
+            id: event.id,
+            event: event.event,
+            outcome: event.outcome,
+            actor_id: event.actor_id,
+            target_id: event.target_id,
+            ip: event.ip,
+            user_agent: event.user_agent,
+            detail: event.detail,
+            created_at: event.created_at,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use entity::data_export;
+
+/// Export job as seen by its owner
+#[derive(Debug, Serialize)]
+pub struct ExportInfo {
+    pub id: i32,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl From<data_export::Model> for ExportInfo {
+    fn from(export: data_export::Model) -> Self {
+        ExportInfo {
+            id: export.id,
+            status: export.status,
+            created_at: export.created_at,
+            finished_at: export.finished_at,
+            expires_at: export.expires_at,
+        }
+    }
+}
+
+/// Account data in `profile.json`
+#[derive(Debug, Serialize)]
+pub struct ExportedProfile {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub location: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    pub banner_url: Option<String>,
+    pub followers: i32,
+    pub following: i32,
+    pub created_at: NaiveDateTime,
+    pub email_verified_at: Option<NaiveDateTime>,
+}
+
+/// Entry of `likes.json`
+#[derive(Debug, Serialize)]
+pub struct ExportedLike {
+    pub post_id: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// Entry of `followers.json` and `following.json`
+#[derive(Debug, Serialize)]
+pub struct ExportedFollow {
+    pub user_id: i32,
+    pub created_at: NaiveDateTime,
+}
+
+/// Entry of `sessions.json`
+#[derive(Debug, Serialize)]
+pub struct ExportedSession {
+    pub id: i32,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
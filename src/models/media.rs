@@ -0,0 +1,38 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use entity::media;
+
+/// Uploaded image
+#[derive(Debug, Serialize)]
+pub struct MediaInfo {
+    pub id: i32,
+    /// Post the image is attached to, missing until then
+    pub post_id: Option<i32>,
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    /// Size of the stored image, in bytes
+    pub size: i32,
+    pub alt_text: Option<String>,
+    pub url: String,
+    pub thumbnail_url: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<media::Model> for MediaInfo {
+    fn from(media: media::Model) -> Self {
+        Self {
+            id: media.id,
+            post_id: media.post_id,
+            content_type: media.content_type,
+            width: media.width,
+            height: media.height,
+            size: media.size,
+            alt_text: media.alt_text,
+            url: format!("/api/media/{}", media.id),
+            thumbnail_url: format!("/api/media/{}/thumbnail", media.id),
+            created_at: media.created_at,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Action guarded by the permission system
+///
+/// Roles are granted permissions in the `role_permission` table,
+/// personal access tokens carry the delegable ones as scopes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    #[serde(rename = "users:read")]
+    UsersRead,
+    #[serde(rename = "users:manage")]
+    UsersManage,
+    #[serde(rename = "posts:read")]
+    PostsRead,
+    #[serde(rename = "posts:write")]
+    PostsWrite,
+    #[serde(rename = "posts:moderate")]
+    PostsModerate,
+    #[serde(rename = "feed:read")]
+    FeedRead,
+    #[serde(rename = "follows:read")]
+    FollowsRead,
+    #[serde(rename = "follows:write")]
+    FollowsWrite,
+    #[serde(rename = "account:manage")]
+    AccountManage,
+    #[serde(rename = "keys:rotate")]
+    KeysRotate,
+    #[serde(rename = "roles:manage")]
+    RolesManage,
+    #[serde(rename = "audit:read")]
+    AuditRead,
+}
+
+impl Permission {
+    /// Whether personal access tokens can carry the permission
+    pub fn is_delegable(&self) -> bool {
+        matches!(
+            self,
+            Permission::UsersRead
+                | Permission::PostsRead
+                | Permission::PostsWrite
+                | Permission::FeedRead
+                | Permission::FollowsRead
+                | Permission::FollowsWrite
+        )
+    }
+
+    /// Parses permissions stored separated by spaces, unknown ones are skipped
+    pub fn parse_list(s: &str) -> Vec<Permission> {
+        s.split_whitespace()
+            .filter_map(|permission| Permission::from_str(permission).ok())
+            .collect()
+    }
+
+    pub fn join(permissions: &[Permission]) -> String {
+        permissions
+            .iter()
+            .map(|permission| permission.to_string())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Permission::UsersRead => write!(f, "users:read"),
+            Permission::UsersManage => write!(f, "users:manage"),
+            Permission::PostsRead => write!(f, "posts:read"),
+            Permission::PostsWrite => write!(f, "posts:write"),
+            Permission::PostsModerate => write!(f, "posts:moderate"),
+            Permission::FeedRead => write!(f, "feed:read"),
+            Permission::FollowsRead => write!(f, "follows:read"),
+            Permission::FollowsWrite => write!(f, "follows:write"),
+            Permission::AccountManage => write!(f, "account:manage"),
+            Permission::KeysRotate => write!(f, "keys:rotate"),
+            Permission::RolesManage => write!(f, "roles:manage"),
+            Permission::AuditRead => write!(f, "audit:read"),
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "users:read" => Ok(Permission::UsersRead),
+            "users:manage" => Ok(Permission::UsersManage),
+            "posts:read" => Ok(Permission::PostsRead),
+            "posts:write" => Ok(Permission::PostsWrite),
+            "posts:moderate" => Ok(Permission::PostsModerate),
+            "feed:read" => Ok(Permission::FeedRead),
+            "follows:read" => Ok(Permission::FollowsRead),
+            "follows:write" => Ok(Permission::FollowsWrite),
+            "account:manage" => Ok(Permission::AccountManage),
+            "keys:rotate" => Ok(Permission::KeysRotate),
+            "roles:manage" => Ok(Permission::RolesManage),
+            "audit:read" => Ok(Permission::AuditRead),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Permission as listed to admins
+#[derive(Debug, Serialize)]
+pub struct PermissionInfo {
+    pub name: String,
+    pub description: String,
+}
@@ -0,0 +1,25 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use super::permission::Permission;
+
+/// Personal access token as seen by its owner, the token itself is never shown again
+#[derive(Debug, Serialize)]
+pub struct PersonalTokenInfo {
+    pub id: i32,
+    pub name: String,
+    pub scopes: Vec<Permission>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+/// Freshly minted personal access token, the only time the token is shown
+#[derive(Debug, Serialize)]
+pub struct CreatedPersonalToken {
+    pub id: i32,
+    pub name: String,
+    pub token: String,
+    pub scopes: Vec<Permission>,
+    pub expires_at: Option<NaiveDateTime>,
+}
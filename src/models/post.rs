@@ -0,0 +1,118 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use entity::post;
+
+use super::media::MediaInfo;
+
+/// How a post relates to the one in `related_to_post`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PostKind {
+    Original,
+    Reply,
+    /// Shares the post as is, has no text of its own
+    Repost,
+    /// Shares the post with a text of its own
+    Quote,
+}
+
+impl PostKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostKind::Original => "original",
+            PostKind::Reply => "reply",
+            PostKind::Repost => "repost",
+            PostKind::Quote => "quote",
+        }
+    }
+
+    pub fn of(post: &post::Model) -> Self {
+        match post.kind.as_str() {
+            "reply" => PostKind::Reply,
+            "repost" => PostKind::Repost,
+            "quote" => PostKind::Quote,
+            _ => PostKind::Original,
+        }
+    }
+}
+
+/// Post as readers see it
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum PostView {
+    Post(post::Model),
+    Tombstone(Tombstone),
+}
+
+impl From<post::Model> for PostView {
+    fn from(post: post::Model) -> Self {
+        match post.deleted_at {
+            Some(deleted_at) => PostView::Tombstone(Tombstone {
+                id: post.id,
+                kind: post.kind,
+                related_to_post: post.related_to_post,
+                comments: post.comments,
+                created_at: post.created_at,
+                deleted_at,
+            }),
+            None => PostView::Post(post),
+        }
+    }
+}
+
+/// Post with its attached images
+#[derive(Debug, Serialize)]
+pub struct PostDetail {
+    #[serde(flatten)]
+    pub post: PostView,
+    pub media: Vec<MediaInfo>,
+}
+
+/// Deleted post, only keeps its place in the thread
+#[derive(Debug, Serialize)]
+pub struct Tombstone {
+    pub id: i32,
+    pub kind: String,
+    pub related_to_post: Option<i32>,
+    pub comments: i32,
+    pub created_at: NaiveDateTime,
+    pub deleted_at: NaiveDateTime,
+}
+
+/// Reply with the replies below it, down to the requested depth
+#[derive(Debug, Serialize)]
+pub struct ReplyTree {
+    #[serde(flatten)]
+    pub post: PostView,
+    /// Empty at the last level, `comments` tells whether there are more
+    pub replies: Vec<ReplyTree>,
+}
+
+/// Conversation around a post
+#[derive(Debug, Serialize)]
+pub struct Thread {
+    /// Posts above, from the root down to the parent
+    pub ancestors: Vec<PostView>,
+    pub post: PostView,
+    pub replies: Vec<ReplyTree>,
+}
+
+/// Post shown in a feed
+#[derive(Debug, Serialize)]
+pub struct FeedItem {
+    /// The reposted post for reposts
+    #[serde(flatten)]
+    pub post: post::Model,
+    /// Post a quote refers to, a tombstone once it was deleted
+    pub quoted_post: Option<PostView>,
+    /// Followed user the post is in the feed for, set for reposts
+    pub reposted_by: Option<Reposter>,
+    pub media: Vec<MediaInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Reposter {
+    pub user_id: i32,
+    pub username: String,
+    pub reposted_at: NaiveDateTime,
+}
@@ -1,52 +1,13 @@
-use std::str::FromStr;
-
-#[derive(Clone, Debug, PartialEq)]
-pub enum Role {
-    Admin = 1,
-    Moderator = 2,
-    User = 3,
-}
-
-impl Role {
-    pub fn from_u8(n: u8) -> Result<Role, String> {
-        match n {
-            1 => Ok(Role::Admin),
-            2 => Ok(Role::Moderator),
-            3 => Ok(Role::User),
-            _ => Err("Expected a number from: 1, 2 or 3".to_owned()),
-        }
-    }
-
-    #[allow(unused)]
-    pub fn to_u8(role: Role) -> u8 {
-        match role {
-            Role::Admin => 1,
-            Role::Moderator => 2,
-            Role::User => 3,
-        }
-    }
-}
-
-impl ToString for Role {
-    fn to_string(&self) -> String {
-        match self {
-            Role::Admin => "admin".to_owned(),
-            Role::Moderator => "moderator".to_owned(),
-            Role::User => "user".to_owned(),
-        }
-    }
-}
-
-impl FromStr for Role {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.to_lowercase();
-        match s.as_str() {
-            "admin" => Ok(Role::Admin),
-            "moderator" => Ok(Role::Moderator),
-            "user" => Ok(Role::User),
-            _ => Err(()),
-        }
-    }
+use serde::Serialize;
+
+use super::permission::Permission;
+
+/// Role with the permissions it grants
+#[derive(Clone, Debug, Serialize)]
+pub struct RoleInfo {
+    pub id: i32,
+    pub name: String,
+    pub permissions: Vec<Permission>,
+    /// Bumped on every change of the permissions
+    pub permissions_version: i32,
 }
@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use super::two_factor::TwoFactorChallenge;
+
+/// Tokens handed out on login and refresh
+#[derive(Debug, Serialize)]
+pub struct AuthTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+}
+
+/// Body of a login or refresh of a cookie session, the tokens are in the cookies
+#[derive(Debug, Serialize)]
+pub struct CookieSession {
+    /// Has to be sent in the `X-CSRF-Token` header of state-changing requests
+    pub csrf_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+}
+
+/// Client a session was started from
+#[derive(Clone, Debug, Default)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Session as seen by its owner
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub id: i32,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub current: bool,
+}
+
+/// Outcome of a login with correct credentials
+#[derive(Debug)]
+pub enum Login {
+    Authenticated(AuthTokens),
+    /// The account has 2FA enabled, a code is needed to finish the login
+    TwoFactorPending(TwoFactorChallenge),
+}
@@ -0,0 +1,21 @@
+use serde::Serialize;
+
+/// Secret of a pending TOTP enrollment, shown to the user once
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// One-time recovery codes, shown to the user once
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Handed out on login instead of tokens when the account has 2FA enabled
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallenge {
+    pub challenge: String,
+    pub expires_in: i64,
+}
@@ -1,3 +1,5 @@
+use chrono::NaiveDateTime;
+use entity::user;
 use sea_orm::FromQueryResult;
 use serde::Serialize;
 
@@ -5,4 +7,61 @@ use serde::Serialize;
 pub struct FollowersOfUser {
     pub id: i32,
     pub username: String,
-}
\ No newline at end of file
+}
+
+/// Role and sanctions of a user as seen by admins
+#[derive(Serialize)]
+pub struct AccountStatus {
+    pub id: i32,
+    pub username: String,
+    pub role: i16,
+    pub suspended_until: Option<NaiveDateTime>,
+    pub banned_at: Option<NaiveDateTime>,
+    pub actions: Vec<AccountActionInfo>,
+}
+
+/// Role change or sanction applied by an admin
+#[derive(Serialize)]
+pub struct AccountActionInfo {
+    pub id: i32,
+    pub admin_id: i32,
+    pub action: String,
+    pub reason: String,
+    pub role: Option<i16>,
+    pub until: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Public profile of a user
+#[derive(Serialize)]
+pub struct Profile {
+    pub id: i32,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub location: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    pub banner_url: Option<String>,
+    pub followers: i32,
+    pub following: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<user::Model> for Profile {
+    fn from(user: user::Model) -> Self {
+        Profile {
+            id: user.id,
+            username: user.username,
+            display_name: user.display_name,
+            bio: user.bio,
+            location: user.location,
+            website: user.website,
+            avatar_url: user.avatar_url,
+            banner_url: user.banner_url,
+            followers: user.followers,
+            following: user.following,
+            created_at: user.created_at,
+        }
+    }
+}
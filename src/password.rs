@@ -0,0 +1,161 @@
+use std::sync::OnceLock;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use subtle::ConstantTimeEq;
+
+use crate::errors::password::PasswordError;
+
+/// Prefix of every Argon2 PHC string, anything else is a legacy plaintext row
+const ARGON2_PREFIX: &str = "$argon2";
+
+static PARAMS: OnceLock<Params> = OnceLock::new();
+
+/// Hash checked when there is no account, hashed with the current parameters
+static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+/// Result of checking a password against the stored value
+#[derive(Debug, PartialEq)]
+pub enum Verification {
+    Invalid,
+    Valid,
+    /// The password matched, but the stored value is plaintext
+    /// or was hashed with outdated cost parameters
+    NeedsRehash,
+}
+
+/// Argon2id cost parameters
+///
+/// Tunable with `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS` and `ARGON2_PARALLELISM`
+fn params() -> &'static Params {
+    PARAMS.get_or_init(|| {
+        let m_cost = env_or("ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST);
+        let t_cost = env_or("ARGON2_ITERATIONS", Params::DEFAULT_T_COST);
+        let p_cost = env_or("ARGON2_PARALLELISM", Params::DEFAULT_P_COST);
+
+        Params::new(m_cost, t_cost, p_cost, None).expect("Invalid Argon2 parameters")
+    })
+}
+
+fn env_or(key: &str, default: u32) -> u32 {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+fn hasher() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params().clone())
+}
+
+/// Hashes the password with a fresh per-user salt
+///
+/// Runs on the blocking thread pool, so it does not stall the executor
+pub async fn hash(password: String) -> Result<String, PasswordError> {
+    tokio::task::spawn_blocking(move || hash_blocking(&password))
+        .await
+        .map_err(|_| PasswordError::Task)?
+}
+
+/// Checks the password against the stored hash or legacy plaintext value
+///
+/// Runs on the blocking thread pool, so it does not stall the executor
+pub async fn verify(password: String, stored: String) -> Result<Verification, PasswordError> {
+    tokio::task::spawn_blocking(move || verify_blocking(&password, &stored))
+        .await
+        .map_err(|_| PasswordError::Task)?
+}
+
+/// Spends the time of a password check without an account to check against,
+/// so the response time does not reveal whether the account exists
+pub async fn verify_dummy(password: String) {
+    let result = tokio::task::spawn_blocking(move || {
+        let stored = DUMMY_HASH.get_or_init(|| {
+            hash_blocking("dummy password").expect("Failed to hash the dummy password")
+        });
+        verify_blocking(&password, stored)
+    })
+    .await;
+
+    if result.is_err() {
+        log::error!("Failed to verify the dummy password");
+    }
+}
+
+fn hash_blocking(password: &str) -> Result<String, PasswordError> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    hasher()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|_| PasswordError::Hashing)
+}
+
+fn verify_blocking(password: &str, stored: &str) -> Result<Verification, PasswordError> {
+    let parsed = match stored.starts_with(ARGON2_PREFIX) {
+        true => PasswordHash::new(stored).ok(),
+        false => None,
+    };
+
+    // legacy rows keep the password as is, which may even look like a hash
+    let Some(parsed) = parsed else {
+        return match bool::from(password.as_bytes().ct_eq(stored.as_bytes())) {
+            true => Ok(Verification::NeedsRehash),
+            false => Ok(Verification::Invalid),
+        };
+    };
+
+    if hasher()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_err()
+    {
+        return Ok(Verification::Invalid);
+    }
+
+    let outdated = parsed.algorithm.as_str() != Algorithm::Argon2id.ident().as_str()
+        || Params::try_from(&parsed).map_or(true, |p| {
+            p.m_cost() != params().m_cost()
+                || p.t_cost() != params().t_cost()
+                || p.p_cost() != params().p_cost()
+        });
+
+    match outdated {
+        true => Ok(Verification::NeedsRehash),
+        false => Ok(Verification::Valid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_hashed_password() {
+        let stored = hash_blocking("correct horse").unwrap();
+
+        assert_eq!(
+            verify_blocking("correct horse", &stored).unwrap(),
+            Verification::Valid
+        );
+        assert_eq!(
+            verify_blocking("wrong horse", &stored).unwrap(),
+            Verification::Invalid
+        );
+    }
+
+    #[test]
+    fn legacy_password_looking_like_a_hash_is_plaintext() {
+        let stored = "$argon2 is my password";
+
+        assert_eq!(
+            verify_blocking(stored, stored).unwrap(),
+            Verification::NeedsRehash
+        );
+        assert_eq!(
+            verify_blocking("other", stored).unwrap(),
+            Verification::Invalid
+        );
+    }
+}
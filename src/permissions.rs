@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{OnceLock, RwLock},
+    time::Duration,
+};
+
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait};
+
+use entity::{role, role_permission};
+
+use crate::{
+    errors::db::DbError,
+    jwt::Claims,
+    models::{permission::Permission, role::RoleInfo},
+};
+
+/// How often every instance re-reads the roles, so changes made elsewhere are picked up
+const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+static ROLES: OnceLock<RwLock<HashMap<i32, RoleInfo>>> = OnceLock::new();
+
+fn roles() -> &'static RwLock<HashMap<i32, RoleInfo>> {
+    ROLES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Reads every role with its permissions
+pub async fn load(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let rows = role::Entity::find()
+        .find_with_related(role_permission::Entity)
+        .all(db)
+        .await?;
+
+    let loaded = rows
+        .into_iter()
+        .map(|(role, granted)| {
+            let permissions = granted
+                .iter()
+                .filter_map(|row| Permission::from_str(&row.permission).ok())
+                .collect();
+
+            (
+                role.id,
+                RoleInfo {
+                    id: role.id,
+                    name: role.name,
+                    permissions,
+                    permissions_version: role.permissions_version,
+                },
+            )
+        })
+        .collect();
+
+    *roles().write().unwrap() = loaded;
+
+    Ok(())
+}
+
+/// Periodically reloads the roles
+pub fn watch(db: DatabaseConnection) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        // the first tick completes immediately
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = load(&db).await {
+                log::error!("Failed to reload roles: {e}");
+            }
+        }
+    });
+}
+
+/// Role with its current permissions, reloading once for roles created elsewhere
+pub async fn role(db: &DatabaseConnection, role_id: i32) -> Result<RoleInfo, DbError> {
+    if let Some(role) = roles().read().unwrap().get(&role_id) {
+        return Ok(role.clone());
+    }
+
+    load(db).await.map_err(|_| DbError::InternalError)?;
+
+    roles()
+        .read()
+        .unwrap()
+        .get(&role_id)
+        .cloned()
+        .ok_or(DbError::NotFound)
+}
+
+/// Every known role
+pub fn all() -> Vec<RoleInfo> {
+    let mut all: Vec<RoleInfo> = roles().read().unwrap().values().cloned().collect();
+    all.sort_by_key(|role| role.id);
+    all
+}
+
+/// Current permissions of the claims
+///
+/// Claims carry the permissions of the role at the time they were issued,
+/// a newer version of the role replaces them, so changes apply without a new login
+pub fn effective(claims: &Claims) -> Vec<Permission> {
+    match roles().read().unwrap().get(&claims.rid) {
+        Some(role) if role.permissions_version > claims.pv => role.permissions.clone(),
+        _ => claims.perms.clone(),
+    }
+}
+
+/// Checks the permission against the claims
+pub fn granted(claims: &Claims, permission: Permission) -> bool {
+    effective(claims).contains(&permission)
+}
@@ -1,2 +1,5 @@
+pub mod account;
+pub mod admin;
 pub mod auth;
+pub mod media;
 pub mod post;
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::validation::{Validate, Violations};
+
+/// Longest accepted profile fields, in characters
+const DISPLAY_NAME_MAX: usize = 50;
+const BIO_MAX: usize = 160;
+const LOCATION_MAX: usize = 30;
+const WEBSITE_MAX: usize = 100;
+const MEDIA_URL_MAX: usize = 255;
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Password confirming the deletion of the own account
+pub struct AccountDeleteRequest {
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Profile fields to change, missing ones stay as they are and empty ones are cleared
+pub struct ProfileUpdateRequest {
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub location: Option<String>,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    pub banner_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// New address, confirmed with the current password
+pub struct EmailChangeRequest {
+    pub password: String,
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// New password, confirmed with the current one
+pub struct PasswordChangeRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+impl Validate for AccountDeleteRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.present("password", &self.password);
+    }
+}
+
+impl Validate for ProfileUpdateRequest {
+    fn validate(&self, violations: &mut Violations) {
+        let fields = [
+            ("display_name", &self.display_name, DISPLAY_NAME_MAX),
+            ("bio", &self.bio, BIO_MAX),
+            ("location", &self.location, LOCATION_MAX),
+        ];
+        for (field, value, max) in fields {
+            if let Some(value) = value {
+                violations.max_length(field, value, max);
+            }
+        }
+
+        let links = [
+            ("website", &self.website, WEBSITE_MAX),
+            ("avatar_url", &self.avatar_url, MEDIA_URL_MAX),
+            ("banner_url", &self.banner_url, MEDIA_URL_MAX),
+        ];
+        for (field, value, max) in links {
+            if let Some(value) = value {
+                violations.link(field, value, max);
+            }
+        }
+    }
+}
+
+impl Validate for EmailChangeRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.present("password", &self.password);
+        violations.email("email", &self.email);
+    }
+}
+
+impl Validate for PasswordChangeRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.present("current_password", &self.current_password);
+        violations.password("new_password", &self.new_password);
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::permission::Permission,
+    validation::{Validate, Violations, INVALID_FORMAT},
+};
+
+/// Longest accepted role name and reason of an account change, in characters
+const ROLE_NAME_MAX: usize = 50;
+const REASON_MAX: usize = 500;
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Custom role and the permissions it grants
+pub struct RoleCreateRequest {
+    pub name: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Permissions replacing the current ones of a role
+pub struct RolePermissionsRequest {
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// New role of a user
+pub struct RoleChangeRequest {
+    pub role_id: i32,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Suspension lasting until the given time, in UTC
+pub struct SuspendRequest {
+    pub until: NaiveDateTime,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Ban or lift of the sanctions of a user
+pub struct SanctionRequest {
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Filters of the audit log, times are in UTC
+pub struct AuditQuery {
+    pub user_id: Option<i32>,
+    pub event: Option<String>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    pub before: Option<i32>,
+    pub limit: Option<u64>,
+}
+
+impl Validate for RoleCreateRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.text("name", &self.name, ROLE_NAME_MAX);
+    }
+}
+
+impl Validate for RoleChangeRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.text("reason", &self.reason, REASON_MAX);
+    }
+}
+
+impl Validate for SuspendRequest {
+    fn validate(&self, violations: &mut Violations) {
+        if self.until <= Utc::now().naive_utc() {
+            violations.add("until", INVALID_FORMAT, "Has to be in the future");
+        }
+        violations.text("reason", &self.reason, REASON_MAX);
+    }
+}
+
+impl Validate for SanctionRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.text("reason", &self.reason, REASON_MAX);
+    }
+}
@@ -1,15 +1,129 @@
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    models::permission::Permission,
+    validation::{Validate, Violations, NOT_ALLOWED, TOO_LONG, TOO_SHORT},
+};
+
+/// Longest accepted name of a personal access token, in characters
+const TOKEN_NAME_MAX: usize = 100;
+/// Longest accepted lifetime of a personal access token, in days
+pub const TOKEN_LIFETIME_MAX_DAYS: u32 = 365;
+
 #[derive(Serialize, Deserialize, Debug)]
-/// Authentication data
+/// Authentication data, logins send either the username or the email
 pub struct AuthRequest {
+    #[serde(default)]
     pub username: String,
     pub password: String,
+    #[serde(default)]
     pub email: String,
+    /// Asks for a cookie session on login instead of tokens in the body
+    #[serde(default)]
+    pub cookie: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 /// Data to logout from a session
 pub struct LogoutRequest {
+    /// Taken from the session cookie when missing
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Token from the verification mail
+pub struct VerifyQuery {
     pub token: String,
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Address to send a new verification mail to
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Address to send the password reset link to
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Token from the password reset mail and the new password
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Data to exchange a refresh token for new tokens
+pub struct RefreshRequest {
+    /// Taken from the session cookie when missing
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Code from the authenticator app, or a recovery code where accepted
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Data to finish a login of an account with 2FA enabled
+pub struct TwoFactorLoginRequest {
+    pub challenge: String,
+    pub code: String,
+    /// Asks for a cookie session instead of tokens in the body
+    #[serde(default)]
+    pub cookie: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+/// Name, scopes and optional lifetime of a new personal access token
+pub struct PersonalTokenCreateRequest {
+    pub name: String,
+    pub scopes: Vec<Permission>,
+    pub expires_in_days: Option<u32>,
+}
+
+/// Rules of registrations, logins are checked against the stored accounts only
+impl Validate for AuthRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.username("username", &self.username);
+        violations.email("email", &self.email);
+        violations.password("password", &self.password);
+    }
+}
+
+impl Validate for ResetPasswordRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.present("token", &self.token);
+        violations.password("password", &self.password);
+    }
+}
+
+impl Validate for PersonalTokenCreateRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.text("name", &self.name, TOKEN_NAME_MAX);
+
+        if self.scopes.iter().any(|scope| !scope.is_delegable()) {
+            violations.add(
+                "scopes",
+                NOT_ALLOWED,
+                "Only scopes that can be given to tokens",
+            );
+        }
+
+        match self.expires_in_days {
+            Some(0) => violations.add("expires_in_days", TOO_SHORT, "At least 1 day"),
+            Some(days) if days > TOKEN_LIFETIME_MAX_DAYS => violations.add(
+                "expires_in_days",
+                TOO_LONG,
+                format!("At most {TOKEN_LIFETIME_MAX_DAYS} days"),
+            ),
+            _ => {}
+        }
+    }
+}
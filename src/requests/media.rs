@@ -0,0 +1,26 @@
+use bytes::Bytes;
+
+use crate::validation::{Validate, Violations, REQUIRED};
+
+/// Longest accepted alt text, in characters
+const ALT_TEXT_MAX: usize = 1000;
+
+#[derive(Debug, Default)]
+/// Fields of the multipart upload form
+pub struct MediaUploadRequest {
+    /// The image, in the `file` part
+    pub file: Option<Bytes>,
+    /// Description of the image for screen readers, in the `alt_text` part
+    pub alt_text: Option<String>,
+}
+
+impl Validate for MediaUploadRequest {
+    fn validate(&self, violations: &mut Violations) {
+        if self.file.as_ref().is_none_or(|file| file.is_empty()) {
+            violations.add("file", REQUIRED, "Can not be empty");
+        }
+        if let Some(alt_text) = &self.alt_text {
+            violations.max_length("alt_text", alt_text, ALT_TEXT_MAX);
+        }
+    }
+}
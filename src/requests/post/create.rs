@@ -1,8 +1,37 @@
 use serde::{Deserialize, Serialize};
 
+use crate::validation::{Validate, Violations, INVALID_FORMAT, TOO_LONG};
+
+/// Longest accepted post, in characters
+const TEXT_MAX: usize = 280;
+
+/// Uploads attached to a post at most
+pub const MEDIA_MAX: usize = 4;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PostCreateRequest {
+    /// Post this one replies to
     pub related_to_post: Option<i32>,
-    pub user_id: i32,
+    /// Post this one quotes
+    pub quote_of: Option<i32>,
     pub text: String,
+    /// Own uploads attached to the post, ignored on edits
+    #[serde(default)]
+    pub media_ids: Vec<i32>,
+}
+
+impl Validate for PostCreateRequest {
+    fn validate(&self, violations: &mut Violations) {
+        violations.text("text", &self.text, TEXT_MAX);
+        if self.related_to_post.is_some() && self.quote_of.is_some() {
+            violations.add(
+                "quote_of",
+                INVALID_FORMAT,
+                "A post can not reply and quote at once",
+            );
+        }
+        if self.media_ids.len() > MEDIA_MAX {
+            violations.add("media_ids", TOO_LONG, format!("At most {MEDIA_MAX} images"));
+        }
+    }
 }
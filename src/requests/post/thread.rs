@@ -0,0 +1,15 @@
+use serde::Deserialize;
+
+/// Page of direct replies, oldest first
+#[derive(Debug, Deserialize)]
+pub struct RepliesQuery {
+    /// Id of the last reply of the previous page
+    pub after: Option<i32>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ThreadQuery {
+    /// Levels of replies below the post
+    pub depth: Option<u32>,
+}
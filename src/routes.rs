@@ -16,11 +16,44 @@ pub fn get_routes(
     // ---  USERS   ---
     // GET                      /users
     // GET | PUT | DELETE       /users/:uuid
+    // PATCH | DELETE           /users/me
+    // POST                     /users/me/email
+    // POST                     /users/me/password
+    // POST                     /users/me/export
+    // GET                      /users/me/export/:id
+    // GET                      /users/me/export/:id/download
 
     // ---  AUTH    ---
     // POST                     /auth/login
     // POST                     /auth/register
     // POST                     /auth/logout
+    // POST                     /auth/refresh
+    // GET                      /auth/verify?token=
+    // POST                     /auth/verify/resend
+    // POST                     /auth/password/forgot
+    // POST                     /auth/password/reset
+    // POST                     /auth/2fa/enroll
+    // POST                     /auth/2fa/confirm
+    // POST                     /auth/2fa/recovery-codes
+    // POST                     /auth/2fa/login
+    // DELETE                   /auth/2fa
+    // GET | DELETE             /auth/sessions
+    // DELETE                   /auth/sessions/:id
+    // GET | POST               /auth/tokens
+    // DELETE                   /auth/tokens/:id
+
+    // ---  ADMIN   ---
+    // POST                     /admin/keys/rotate
+    // GET                      /admin/permissions
+    // GET | POST               /admin/roles
+    // PUT                      /admin/roles/:id/permissions
+    // DELETE                   /admin/roles/:id
+    // GET                      /admin/users/:id
+    // PUT                      /admin/users/:id/role
+    // POST                     /admin/users/:id/suspension
+    // POST                     /admin/users/:id/ban
+    // DELETE                   /admin/users/:id/sanctions
+    // GET                      /admin/audit
 
     // --- POST     ---
     // POST                     /posts
@@ -28,13 +61,29 @@ pub fn get_routes(
     // GET                      /posts/:uuid
     // PATCH                    /posts/:uuid
     // DELETE                   /posts/:uuid
+    // POST                     /posts/:uuid/like
+    // POST                     /posts/:uuid/repost
+    // GET                      /posts/:uuid/replies
+    // GET                      /posts/:uuid/thread
+    // GET                      /posts/:uuid/revisions
+
+    // --- MEDIA    ---
+    // POST                     /media
+    // GET                      /media/:id
+    // GET                      /media/:id/thumbnail
+
+    // --- WELL-KNOWN (outside of /api) ---
+    // GET                      /.well-known/jwks.json
     //
     warp::path("api")
         .and(
             filters::users::users(session.clone())
                 .or(filters::auth::auth(session.clone()))
-                .or(filters::posts::posts(session.clone())),
+                .or(filters::posts::posts(session.clone()))
+                .or(filters::media::media(session.clone()))
+                .or(filters::admin::admin(session.clone())),
         )
+        .or(filters::well_known::well_known())
         .with(warp::cors().allow_any_origin())
         .recover(handle_rejection)
 }
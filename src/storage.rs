@@ -0,0 +1,47 @@
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::errors::storage::StorageError;
+
+use self::{local::LocalStorage, s3::S3Storage};
+
+pub mod local;
+pub mod s3;
+
+static STORAGE: OnceLock<Box<dyn Storage>> = OnceLock::new();
+
+/// Where uploaded media is kept
+///
+/// Keys are generated by the server and only contain `[A-Za-z0-9_/.-]`
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, data: Bytes) -> Result<(), StorageError>;
+
+    /// Returns `None` when there is nothing under the key
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, StorageError>;
+
+    /// Deleting a missing key is not an error
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// Selects the backend with `MEDIA_STORAGE`: `local` (default) or `s3`
+pub async fn init() -> Result<(), String> {
+    let storage: Box<dyn Storage> = match std::env::var("MEDIA_STORAGE").as_deref() {
+        Ok("s3") => Box::new(S3Storage::from_env().await?),
+        Ok("local") | Err(_) => Box::new(LocalStorage::from_env()?),
+        Ok(other) => return Err(format!("Unknown MEDIA_STORAGE `{other}`")),
+    };
+
+    STORAGE
+        .set(storage)
+        .map_err(|_| "Media storage is already initialized".to_owned())
+}
+
+pub fn storage() -> &'static dyn Storage {
+    STORAGE
+        .get()
+        .expect("Media storage is not initialized")
+        .as_ref()
+}
@@ -0,0 +1,52 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use crate::errors::storage::StorageError;
+
+use super::Storage;
+
+/// Keeps the files in a directory of the server
+pub struct LocalStorage {
+    dir: PathBuf,
+}
+
+impl LocalStorage {
+    /// Writes to `MEDIA_DIR`, `./media` by default
+    pub fn from_env() -> Result<LocalStorage, String> {
+        let dir = std::env::var("MEDIA_DIR").unwrap_or_else(|_| "./media".to_owned());
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {dir}: {e}"))?;
+
+        Ok(LocalStorage {
+            dir: PathBuf::from(dir),
+        })
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, _content_type: &str, data: Bytes) -> Result<(), StorageError> {
+        let path = self.dir.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::write(path, data).await.map_err(Into::into)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, StorageError> {
+        match tokio::fs::read(self.dir.join(key)).await {
+            Ok(data) => Ok(Some(Bytes::from(data))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.dir.join(key)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_s3::{error::DisplayErrorContext, primitives::ByteStream, Client};
+use bytes::Bytes;
+
+use crate::errors::storage::StorageError;
+
+use super::Storage;
+
+/// Keeps the files in a bucket of S3 or an S3-compatible server like MinIO
+pub struct S3Storage {
+    client: Client,
+    bucket: String,
+}
+
+impl S3Storage {
+    /// Writes to `MEDIA_S3_BUCKET` in `MEDIA_S3_REGION`, `us-east-1` by default
+    ///
+    /// `MEDIA_S3_ENDPOINT` points at an S3-compatible server, which is then
+    /// addressed with path-style URLs; credentials come from the usual
+    /// `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`
+    pub async fn from_env() -> Result<S3Storage, String> {
+        let bucket = std::env::var("MEDIA_S3_BUCKET")
+            .map_err(|_| "MEDIA_S3_BUCKET is not set".to_owned())?;
+        let region = std::env::var("MEDIA_S3_REGION").unwrap_or_else(|_| "us-east-1".to_owned());
+
+        let shared = aws_config::defaults(BehaviorVersion::latest())
+            .region(Region::new(region))
+            .load()
+            .await;
+
+        let mut config = aws_sdk_s3::config::Builder::from(&shared);
+        let endpoint = std::env::var("MEDIA_S3_ENDPOINT").unwrap_or_default();
+        if !endpoint.is_empty() {
+            config = config.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        Ok(S3Storage {
+            client: Client::from_conf(config.build()),
+            bucket,
+        })
+    }
+}
+
+fn s3_error(e: impl std::error::Error) -> StorageError {
+    StorageError::S3(DisplayErrorContext(e).to_string())
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, content_type: &str, data: Bytes) -> Result<(), StorageError> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(s3_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, StorageError> {
+        let object = match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(object) => object,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => return Ok(None),
+            Err(e) => return Err(s3_error(e)),
+        };
+
+        object
+            .body
+            .collect()
+            .await
+            .map(|data| Some(data.into_bytes()))
+            .map_err(s3_error)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(s3_error)
+    }
+}
@@ -0,0 +1,334 @@
+use std::{net::SocketAddr, sync::OnceLock, time::Duration as StdDuration};
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::DatabaseConnection;
+use warp::{reject, Rejection};
+
+use crate::errors::throttle::ThrottleError;
+
+use self::{memory::MemoryStore, postgres::PostgresStore};
+
+pub mod memory;
+pub mod postgres;
+
+/// Failed logins an account gets before it is locked out
+const FREE_FAILURES: u32 = 3;
+
+/// Upper bound of the lockout, in seconds
+const MAX_LOCKOUT: i64 = 60 * 15;
+
+/// Failures are forgotten after this long without another one, in seconds
+const FAILURE_WINDOW: i64 = 60 * 60 * 24;
+
+/// How often state without any effect is dropped
+const PURGE_INTERVAL: StdDuration = StdDuration::from_secs(60 * 10);
+
+static STORE: OnceLock<Box<dyn ThrottleStore>> = OnceLock::new();
+static AUTH_RATE: OnceLock<Rate> = OnceLock::new();
+
+/// Token bucket, holding `burst` tokens and getting one back every `interval`
+#[derive(Debug, Clone, Copy)]
+pub struct Rate {
+    pub burst: u32,
+    pub interval: Duration,
+}
+
+/// Failed logins of an account
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Failures {
+    pub count: u32,
+    pub last_failed_at: NaiveDateTime,
+}
+
+/// Outcome of a login attempt against an account
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attempt {
+    /// Counted as failed until the login succeeds
+    Counted(Failures),
+    /// Refused, the account is locked out for this long
+    Locked(Duration),
+}
+
+/// Storage of throttling state
+///
+/// Buckets are tracked as the time they are full again (GCRA),
+/// so a single timestamp per key is enough
+#[async_trait]
+pub trait ThrottleStore: Send + Sync {
+    /// Takes a token from the bucket of the key
+    ///
+    /// Returns `None` when a token was taken, otherwise how long until one is available
+    async fn take(
+        &self,
+        key: &str,
+        rate: Rate,
+        now: NaiveDateTime,
+    ) -> Result<Option<Duration>, ThrottleError>;
+
+    /// Counts a login attempt as failed unless the account is locked out,
+    /// failures before `since` are forgotten
+    ///
+    /// Checking and counting is a single operation, so parallel attempts can not slip past the lockout
+    async fn attempt(
+        &self,
+        account: &str,
+        now: NaiveDateTime,
+        since: NaiveDateTime,
+    ) -> Result<Attempt, ThrottleError>;
+
+    async fn clear_failures(&self, account: &str) -> Result<(), ThrottleError>;
+
+    /// Drops full buckets and failures before `since`
+    async fn purge(&self, now: NaiveDateTime, since: NaiveDateTime) -> Result<(), ThrottleError>;
+}
+
+/// Selects the store with `THROTTLE_STORE`: `memory` (default) or `postgres`,
+/// the latter shares the state between instances
+pub fn init(db: &DatabaseConnection) -> Result<(), String> {
+    let store: Box<dyn ThrottleStore> = match std::env::var("THROTTLE_STORE").as_deref() {
+        Ok("postgres") => Box::new(PostgresStore::new(db.clone())),
+        Ok("memory") | Err(_) => Box::new(MemoryStore::default()),
+        Ok(other) => return Err(format!("Unknown THROTTLE_STORE `{other}`")),
+    };
+
+    STORE
+        .set(store)
+        .map_err(|_| "Throttle store is already initialized".to_owned())
+}
+
+fn store() -> &'static dyn ThrottleStore {
+    STORE
+        .get()
+        .expect("Throttle store is not initialized")
+        .as_ref()
+}
+
+/// Bucket of every client on the auth routes
+///
+/// Tunable with `THROTTLE_AUTH_BURST` and `THROTTLE_AUTH_INTERVAL_SECS`
+fn auth_rate() -> Rate {
+    *AUTH_RATE.get_or_init(|| Rate {
+        burst: env_or("THROTTLE_AUTH_BURST", 10),
+        interval: Duration::seconds(env_or("THROTTLE_AUTH_INTERVAL_SECS", 6).into()),
+    })
+}
+
+fn env_or(key: &str, default: u32) -> u32 {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Periodically drops state which no longer has any effect
+pub fn watch() {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            let now = Utc::now().naive_utc();
+            if let Err(e) = store()
+                .purge(now, now - Duration::seconds(FAILURE_WINDOW))
+                .await
+            {
+                log::error!("Failed to purge throttle state: {e}");
+            }
+        }
+    });
+}
+
+/// Takes a token from the auth bucket of the client address
+///
+/// The store failing lets the request through, throttling is not worth an outage
+pub async fn check_client(addr: Option<SocketAddr>) -> Result<(), Rejection> {
+    let key = match addr {
+        Some(addr) => format!("auth:{}", addr.ip()),
+        None => return Ok(()),
+    };
+
+    match store()
+        .take(&key, auth_rate(), Utc::now().naive_utc())
+        .await
+    {
+        Ok(None) => Ok(()),
+        Ok(Some(wait)) => Err(reject::custom(ThrottleError::TooManyRequests {
+            retry_after: seconds(wait),
+        })),
+        Err(e) => {
+            log::error!("Failed to throttle {key}: {e}");
+            Ok(())
+        }
+    }
+}
+
+/// Counts a login attempt against the account, refused during its backoff after failed attempts
+///
+/// The attempt counts as failed until `clear_failures` is called after a successful login
+pub async fn count_attempt(account: &str) -> Result<(), Rejection> {
+    let now = Utc::now().naive_utc();
+
+    match store()
+        .attempt(account, now, now - Duration::seconds(FAILURE_WINDOW))
+        .await
+    {
+        Ok(Attempt::Counted(failures)) if failures.count > FREE_FAILURES => {
+            log::warn!(
+                "{} failed logins to account {account}, locking it for {}s",
+                failures.count,
+                lockout(failures.count).num_seconds()
+            );
+            Ok(())
+        }
+        Ok(Attempt::Counted(_)) => Ok(()),
+        Ok(Attempt::Locked(wait)) => Err(reject::custom(ThrottleError::AccountLocked {
+            retry_after: seconds(wait),
+        })),
+        Err(e) => {
+            log::error!("Failed to count login attempt: {e}");
+            Ok(())
+        }
+    }
+}
+
+pub async fn clear_failures(account: &str) {
+    if let Err(e) = store().clear_failures(account).await {
+        log::error!("Failed to clear failed logins: {e}");
+    }
+}
+
+/// Key of the account a login attempt is counted against
+///
+/// Existing accounts are keyed by id, so logins by username and by email share one bucket,
+/// identifiers without an account are keyed by themselves
+pub fn account_key(user_id: Option<i32>, identifier: &str) -> String {
+    match user_id {
+        Some(id) => user_key(id),
+        None => identifier.trim().to_lowercase(),
+    }
+}
+
+/// Key of an existing account, shared by passwords and second factors
+pub fn user_key(user_id: i32) -> String {
+    format!("user:{user_id}")
+}
+
+/// Counts an attempt on top of the previous failures, unless their lockout still lasts
+fn next_attempt(previous: Option<Failures>, now: NaiveDateTime, since: NaiveDateTime) -> Attempt {
+    let previous = previous.filter(|f| f.last_failed_at >= since);
+
+    if let Some(previous) = previous {
+        let locked_until = previous.last_failed_at + lockout(previous.count);
+        if locked_until > now {
+            return Attempt::Locked(locked_until - now);
+        }
+    }
+
+    Attempt::Counted(Failures {
+        count: previous.map_or(0, |f| f.count) + 1,
+        last_failed_at: now,
+    })
+}
+
+/// Doubles with every failure after the free ones
+fn lockout(failures: u32) -> Duration {
+    if failures <= FREE_FAILURES {
+        return Duration::zero();
+    }
+
+    let exponent = (failures - FREE_FAILURES - 1).min(16);
+    Duration::seconds((1_i64 << exponent).min(MAX_LOCKOUT))
+}
+
+/// Whole seconds to wait, rounded up
+fn seconds(wait: Duration) -> u64 {
+    let millis = wait.num_milliseconds().max(0) as u64;
+    millis.div_ceil(1000).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    fn failures(count: u32, seconds_ago: i64) -> Failures {
+        Failures {
+            count,
+            last_failed_at: now() - Duration::seconds(seconds_ago),
+        }
+    }
+
+    fn since() -> NaiveDateTime {
+        now() - Duration::seconds(FAILURE_WINDOW)
+    }
+
+    #[test]
+    fn free_failures_are_not_locked_out() {
+        for count in 0..=FREE_FAILURES {
+            assert_eq!(lockout(count), Duration::zero());
+        }
+    }
+
+    #[test]
+    fn lockout_doubles_up_to_the_limit() {
+        assert_eq!(lockout(FREE_FAILURES + 1), Duration::seconds(1));
+        assert_eq!(lockout(FREE_FAILURES + 2), Duration::seconds(2));
+        assert_eq!(lockout(FREE_FAILURES + 5), Duration::seconds(16));
+        assert_eq!(lockout(FREE_FAILURES + 11), Duration::seconds(MAX_LOCKOUT));
+        assert_eq!(lockout(u32::MAX), Duration::seconds(MAX_LOCKOUT));
+    }
+
+    #[test]
+    fn first_attempt_is_counted() {
+        assert_eq!(
+            next_attempt(None, now(), since()),
+            Attempt::Counted(failures(1, 0))
+        );
+    }
+
+    #[test]
+    fn attempt_during_lockout_is_refused() {
+        let previous = failures(FREE_FAILURES + 3, 1);
+
+        assert_eq!(
+            next_attempt(Some(previous), now(), since()),
+            Attempt::Locked(Duration::seconds(3))
+        );
+    }
+
+    #[test]
+    fn attempt_after_lockout_is_counted() {
+        let previous = failures(FREE_FAILURES + 3, 4);
+
+        assert_eq!(
+            next_attempt(Some(previous), now(), since()),
+            Attempt::Counted(failures(FREE_FAILURES + 4, 0))
+        );
+    }
+
+    #[test]
+    fn failures_outside_the_window_are_forgotten() {
+        let previous = failures(FREE_FAILURES + 20, FAILURE_WINDOW + 1);
+
+        assert_eq!(
+            next_attempt(Some(previous), now(), since()),
+            Attempt::Counted(failures(1, 0))
+        );
+    }
+
+    #[test]
+    fn wait_is_rounded_up_to_whole_seconds() {
+        assert_eq!(seconds(Duration::milliseconds(1500)), 2);
+        assert_eq!(seconds(Duration::seconds(2)), 2);
+        assert_eq!(seconds(Duration::zero()), 1);
+    }
+}
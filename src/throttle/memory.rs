@@ -0,0 +1,164 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime};
+
+use crate::errors::throttle::ThrottleError;
+
+use super::{next_attempt, Attempt, Failures, Rate, ThrottleStore};
+
+/// Keeps the state of this instance only
+#[derive(Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, NaiveDateTime>>,
+    failures: Mutex<HashMap<String, Failures>>,
+}
+
+#[async_trait]
+impl ThrottleStore for MemoryStore {
+    async fn take(
+        &self,
+        key: &str,
+        rate: Rate,
+        now: NaiveDateTime,
+    ) -> Result<Option<Duration>, ThrottleError> {
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let full_at = buckets.get(key).copied().unwrap_or(now).max(now) + rate.interval;
+        let allowed = rate.interval * rate.burst as i32;
+
+        if full_at - now > allowed {
+            return Ok(Some(full_at - now - allowed));
+        }
+
+        buckets.insert(key.to_owned(), full_at);
+
+        Ok(None)
+    }
+
+    async fn attempt(
+        &self,
+        account: &str,
+        now: NaiveDateTime,
+        since: NaiveDateTime,
+    ) -> Result<Attempt, ThrottleError> {
+        let mut failures = self.failures.lock().unwrap();
+
+        let attempt = next_attempt(failures.get(account).copied(), now, since);
+        if let Attempt::Counted(counted) = attempt {
+            failures.insert(account.to_owned(), counted);
+        }
+
+        Ok(attempt)
+    }
+
+    async fn clear_failures(&self, account: &str) -> Result<(), ThrottleError> {
+        self.failures.lock().unwrap().remove(account);
+        Ok(())
+    }
+
+    async fn purge(&self, now: NaiveDateTime, since: NaiveDateTime) -> Result<(), ThrottleError> {
+        self.buckets
+            .lock()
+            .unwrap()
+            .retain(|_, full_at| *full_at > now);
+        self.failures
+            .lock()
+            .unwrap()
+            .retain(|_, failures| failures.last_failed_at >= since);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    fn rate() -> Rate {
+        Rate {
+            burst: 3,
+            interval: Duration::seconds(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn take_allows_the_burst() {
+        let store = MemoryStore::default();
+
+        for _ in 0..3 {
+            assert_eq!(store.take("a", rate(), now()).await.unwrap(), None);
+        }
+        assert_eq!(
+            store.take("a", rate(), now()).await.unwrap(),
+            Some(Duration::seconds(10))
+        );
+    }
+
+    #[tokio::test]
+    async fn take_refills_one_token_per_interval() {
+        let store = MemoryStore::default();
+
+        for _ in 0..3 {
+            store.take("a", rate(), now()).await.unwrap();
+        }
+
+        let later = now() + Duration::seconds(4);
+        assert_eq!(
+            store.take("a", rate(), later).await.unwrap(),
+            Some(Duration::seconds(6))
+        );
+
+        let later = now() + Duration::seconds(10);
+        assert_eq!(store.take("a", rate(), later).await.unwrap(), None);
+        assert_eq!(
+            store.take("a", rate(), later).await.unwrap(),
+            Some(Duration::seconds(10))
+        );
+    }
+
+    #[tokio::test]
+    async fn take_keeps_keys_apart() {
+        let store = MemoryStore::default();
+
+        for _ in 0..3 {
+            store.take("a", rate(), now()).await.unwrap();
+        }
+
+        assert_eq!(store.take("b", rate(), now()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn attempts_lock_the_account_out() {
+        let store = MemoryStore::default();
+        let since = now() - Duration::days(1);
+
+        for count in 1..=4 {
+            assert_eq!(
+                store.attempt("user:1", now(), since).await.unwrap(),
+                Attempt::Counted(Failures {
+                    count,
+                    last_failed_at: now(),
+                })
+            );
+        }
+        assert_eq!(
+            store.attempt("user:1", now(), since).await.unwrap(),
+            Attempt::Locked(Duration::seconds(1))
+        );
+
+        store.clear_failures("user:1").await.unwrap();
+        assert!(matches!(
+            store.attempt("user:1", now(), since).await.unwrap(),
+            Attempt::Counted(Failures { count: 1, .. })
+        ));
+    }
+}
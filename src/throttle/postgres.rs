@@ -0,0 +1,162 @@
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDateTime};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, QueryFilter,
+    Statement, TransactionTrait,
+};
+
+use entity::{login_failure, throttle_bucket};
+
+use crate::errors::throttle::ThrottleError;
+
+use super::{next_attempt, Attempt, Failures, Rate, ThrottleStore};
+
+/// Shares the state between every instance using the database
+pub struct PostgresStore {
+    db: DatabaseConnection,
+}
+
+impl PostgresStore {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl ThrottleStore for PostgresStore {
+    async fn take(
+        &self,
+        key: &str,
+        rate: Rate,
+        now: NaiveDateTime,
+    ) -> Result<Option<Duration>, ThrottleError> {
+        let interval = rate.interval.num_milliseconds() as f64 / 1000.0;
+        let allowed = interval * rate.burst as f64;
+
+        // the row is only touched while a token is left, so checking and taking is atomic
+        let taken = self
+            .db
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"INSERT INTO throttle_bucket AS b (key, full_at)
+                VALUES ($1, $2 + make_interval(secs => $3))
+                ON CONFLICT (key) DO UPDATE
+                SET full_at = GREATEST(b.full_at, $2) + make_interval(secs => $3)
+                WHERE GREATEST(b.full_at, $2) + make_interval(secs => $3)
+                    <= $2 + make_interval(secs => $4)
+                RETURNING full_at"#,
+                vec![key.into(), now.into(), interval.into(), allowed.into()],
+            ))
+            .await
+            .map_err(|e| ThrottleError::Store(e.to_string()))?;
+
+        if taken.is_some() {
+            return Ok(None);
+        }
+
+        let bucket = throttle_bucket::Entity::find_by_id(key.to_owned())
+            .one(&self.db)
+            .await
+            .map_err(|e| ThrottleError::Store(e.to_string()))?;
+
+        Ok(Some(match bucket {
+            Some(bucket) => {
+                bucket.full_at.max(now) + rate.interval - now - rate.interval * rate.burst as i32
+            }
+            None => Duration::zero(),
+        }))
+    }
+
+    async fn attempt(
+        &self,
+        account: &str,
+        now: NaiveDateTime,
+        since: NaiveDateTime,
+    ) -> Result<Attempt, ThrottleError> {
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| ThrottleError::Store(e.to_string()))?;
+
+        // the row is locked until the attempt is counted, so parallel attempts wait for each other
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO login_failure (account, failures, last_failed_at)
+            VALUES ($1, 0, $2)
+            ON CONFLICT (account) DO NOTHING"#,
+            vec![account.into(), now.into()],
+        ))
+        .await
+        .map_err(|e| ThrottleError::Store(e.to_string()))?;
+
+        let row = txn
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"SELECT failures, last_failed_at FROM login_failure
+                WHERE account = $1
+                FOR UPDATE"#,
+                vec![account.into()],
+            ))
+            .await
+            .map_err(|e| ThrottleError::Store(e.to_string()))?
+            .ok_or_else(|| ThrottleError::Store("Failed logins were not found".to_owned()))?;
+
+        let count: i32 = row
+            .try_get("", "failures")
+            .map_err(|e| ThrottleError::Store(e.to_string()))?;
+        let last_failed_at = row
+            .try_get("", "last_failed_at")
+            .map_err(|e| ThrottleError::Store(e.to_string()))?;
+
+        let previous = Failures {
+            count: count as u32,
+            last_failed_at,
+        };
+        let attempt = next_attempt(Some(previous).filter(|f| f.count > 0), now, since);
+
+        if let Attempt::Counted(counted) = attempt {
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"UPDATE login_failure SET failures = $2, last_failed_at = $3
+                WHERE account = $1"#,
+                vec![
+                    account.into(),
+                    (counted.count as i32).into(),
+                    counted.last_failed_at.into(),
+                ],
+            ))
+            .await
+            .map_err(|e| ThrottleError::Store(e.to_string()))?;
+        }
+
+        txn.commit()
+            .await
+            .map_err(|e| ThrottleError::Store(e.to_string()))?;
+
+        Ok(attempt)
+    }
+
+    async fn clear_failures(&self, account: &str) -> Result<(), ThrottleError> {
+        login_failure::Entity::delete_by_id(account.to_owned())
+            .exec(&self.db)
+            .await
+            .map(|_| ())
+            .map_err(|e| ThrottleError::Store(e.to_string()))
+    }
+
+    async fn purge(&self, now: NaiveDateTime, since: NaiveDateTime) -> Result<(), ThrottleError> {
+        throttle_bucket::Entity::delete_many()
+            .filter(throttle_bucket::Column::FullAt.lte(now))
+            .exec(&self.db)
+            .await
+            .map_err(|e| ThrottleError::Store(e.to_string()))?;
+
+        login_failure::Entity::delete_many()
+            .filter(login_failure::Column::LastFailedAt.lt(since))
+            .exec(&self.db)
+            .await
+            .map(|_| ())
+            .map_err(|e| ThrottleError::Store(e.to_string()))
+    }
+}
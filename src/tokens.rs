@@ -0,0 +1,21 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use ring::digest::{digest, SHA256};
+
+/// Length of opaque tokens in bytes
+const TOKEN_LENGTH: usize = 32;
+
+/// Creates a random opaque token, which is handed to the client
+pub fn generate() -> String {
+    let mut token = [0u8; TOKEN_LENGTH];
+    rand::thread_rng().fill_bytes(&mut token);
+
+    URL_SAFE_NO_PAD.encode(token)
+}
+
+/// Hashes an opaque token, only the hash is stored
+///
+/// Tokens are random, so a fast hash is enough to make a DB leak useless
+pub fn hash(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, token.as_bytes()))
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, DbErr, Statement};
+
+/// Days a deleted post stays as a tombstone by default
+const RETENTION_DAYS: i64 = 30;
+
+/// How often old tombstones are purged
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Days before tombstones are purged
+///
+/// Set with `POST_TOMBSTONE_RETENTION_DAYS`
+fn retention() -> chrono::Duration {
+    let days = std::env::var("POST_TOMBSTONE_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(RETENTION_DAYS);
+
+    chrono::Duration::days(days)
+}
+
+/// Periodically hard-deletes old tombstones nothing depends on
+pub fn watch(db: DatabaseConnection) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match purge(&db).await {
+                Ok(0) => (),
+                Ok(purged) => log::info!("Purged {purged} deleted posts"),
+                Err(e) => log::error!("Failed to purge deleted posts: {e}"),
+            }
+        }
+    });
+}
+
+async fn purge(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let deleted_before = Utc::now().naive_utc() - retention();
+    let mut purged = 0;
+
+    // a purged reply can leave its parent without dependents,
+    // so tombstones are purged from the leaves up
+    loop {
+        let result = db
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"DELETE FROM post p
+                WHERE p.deleted_at < $1
+                AND NOT EXISTS (SELECT 1 FROM post r WHERE r.related_to_post = p.id)"#,
+                [deleted_before.into()],
+            ))
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(purged);
+        }
+        purged += result.rows_affected();
+    }
+}
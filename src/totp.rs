@@ -0,0 +1,102 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::Rng;
+use subtle::ConstantTimeEq;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::errors::totp::TotpError;
+
+/// Shown in authenticator apps next to the account name
+const ISSUER: &str = "Nova";
+
+const DIGITS: usize = 6;
+
+/// Length of a time step in seconds
+const STEP: u64 = 30;
+
+/// How many steps around the current one are accepted, to allow for clock drift
+const SKEW: u64 = 1;
+
+/// How many recovery codes are issued at once
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Characters recovery codes are made of, without the easily confused ones
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Creates a new base32 encoded shared secret
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+/// Link authenticator apps read the secret from, usually shown as a QR code
+pub fn otpauth_uri(secret: &str, account: &str) -> Result<String, TotpError> {
+    Ok(totp(secret, account)?.get_url())
+}
+
+/// Checks the code against the secret
+///
+/// Returns the time step the code belongs to. Steps up to `last_step` are refused,
+/// so a code can not be replayed
+pub fn verify(secret: &str, code: &str, last_step: Option<i64>) -> Result<Option<i64>, TotpError> {
+    let totp = totp(secret, "")?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| TotpError::Setup(e.to_string()))?
+        .as_secs();
+    let current = now / STEP;
+
+    for step in current.saturating_sub(SKEW)..=current + SKEW {
+        let expected = totp.generate(step * STEP);
+        let step = step as i64;
+
+        if bool::from(expected.as_bytes().ct_eq(code.trim().as_bytes()))
+            && last_step.is_none_or(|last| step > last)
+        {
+            return Ok(Some(step));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Creates a fresh set of one-time recovery codes, handed to the user once
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code: String = (0..10)
+                .map(|_| {
+                    RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char
+                })
+                .collect();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+/// Brings a recovery code typed by the user into the form it was hashed in
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn totp(secret: &str, account: &str) -> Result<TOTP, TotpError> {
+    let secret = Secret::Encoded(secret.to_owned())
+        .to_bytes()
+        .map_err(|_| TotpError::MalformedSecret)?;
+
+    // ':' separates the issuer from the account in the otpauth label
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        SKEW as u8,
+        STEP,
+        secret,
+        Some(ISSUER.to_owned()),
+        account.replace(':', ""),
+    )
+    .map_err(|e| TotpError::Setup(e.to_string()))
+}
@@ -0,0 +1,274 @@
+use warp::{reject, Rejection};
+
+use crate::errors::validation::{FieldError, ValidationError};
+
+pub const REQUIRED: &str = "required";
+pub const TOO_SHORT: &str = "too_short";
+pub const TOO_LONG: &str = "too_long";
+pub const INVALID_FORMAT: &str = "invalid_format";
+pub const TAKEN: &str = "taken";
+pub const UNAVAILABLE: &str = "unavailable";
+pub const NOT_ALLOWED: &str = "not_allowed";
+
+const USERNAME_MIN: usize = 3;
+const USERNAME_MAX: usize = 30;
+const EMAIL_MAX: usize = 254;
+const PASSWORD_MIN: usize = 8;
+/// Keeps hashing cheap for bodies with huge passwords
+const PASSWORD_MAX: usize = 128;
+
+/// Rules the fields of a request body follow
+pub trait Validate {
+    fn validate(&self, violations: &mut Violations);
+}
+
+/// Broken rules of a request, at most one per field
+#[derive(Debug, Default)]
+pub struct Violations {
+    errors: Vec<FieldError>,
+}
+
+impl Violations {
+    pub fn add(&mut self, field: &'static str, code: &'static str, message: impl Into<String>) {
+        if self.errors.iter().any(|error| error.field == field) {
+            return;
+        }
+
+        self.errors.push(FieldError {
+            field,
+            code,
+            message: message.into(),
+        });
+    }
+
+    /// Not blank and at most `max` characters once trimmed
+    pub fn text(&mut self, field: &'static str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.add(field, REQUIRED, "Can not be empty");
+        } else {
+            self.max_length(field, value, max);
+        }
+    }
+
+    /// At most `max` characters once trimmed, blank values are fine
+    pub fn max_length(&mut self, field: &'static str, value: &str, max: usize) {
+        if value.trim().chars().count() > max {
+            self.add(field, TOO_LONG, format!("At most {max} characters"));
+        }
+    }
+
+    /// Letters, digits and underscores, compared regardless of case
+    pub fn username(&mut self, field: &'static str, value: &str) {
+        let value = value.trim();
+        let length = value.chars().count();
+
+        if value.is_empty() {
+            self.add(field, REQUIRED, "Can not be empty");
+        } else if length < USERNAME_MIN {
+            self.add(
+                field,
+                TOO_SHORT,
+                format!("At least {USERNAME_MIN} characters"),
+            );
+        } else if length > USERNAME_MAX {
+            self.add(
+                field,
+                TOO_LONG,
+                format!("At most {USERNAME_MAX} characters"),
+            );
+        } else if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            self.add(
+                field,
+                INVALID_FORMAT,
+                "Only letters, digits and underscores",
+            );
+        }
+    }
+
+    pub fn email(&mut self, field: &'static str, value: &str) {
+        let value = value.trim();
+
+        let well_formed = match value.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain.contains('.')
+                    && !domain.starts_with('.')
+                    && !domain.ends_with('.')
+                    && !value.chars().any(char::is_whitespace)
+            }
+            None => false,
+        };
+
+        if value.is_empty() {
+            self.add(field, REQUIRED, "Can not be empty");
+        } else if value.chars().count() > EMAIL_MAX {
+            self.add(field, TOO_LONG, format!("At most {EMAIL_MAX} characters"));
+        } else if !well_formed {
+            self.add(field, INVALID_FORMAT, "Has to be an email address");
+        }
+    }
+
+    /// Rules of new passwords, current ones are only checked for presence
+    pub fn password(&mut self, field: &'static str, value: &str) {
+        let length = value.chars().count();
+
+        if value.is_empty() {
+            self.add(field, REQUIRED, "Can not be empty");
+        } else if length < PASSWORD_MIN {
+            self.add(
+                field,
+                TOO_SHORT,
+                format!("At least {PASSWORD_MIN} characters"),
+            );
+        } else if length > PASSWORD_MAX {
+            self.add(
+                field,
+                TOO_LONG,
+                format!("At most {PASSWORD_MAX} characters"),
+            );
+        }
+    }
+
+    pub fn present(&mut self, field: &'static str, value: &str) {
+        if value.is_empty() {
+            self.add(field, REQUIRED, "Can not be empty");
+        }
+    }
+
+    /// An http(s) link, blank values are fine
+    pub fn link(&mut self, field: &'static str, value: &str, max: usize) {
+        let value = value.trim();
+
+        if value.is_empty() {
+            return;
+        }
+        if !value.starts_with("https://") && !value.starts_with("http://") {
+            self.add(field, INVALID_FORMAT, "Has to be an http(s) link");
+        } else {
+            self.max_length(field, value, max);
+        }
+    }
+}
+
+/// Checks the body against the rules of its request
+pub fn check<T: Validate>(body: &T) -> Result<(), ValidationError> {
+    let mut violations = Violations::default();
+    body.validate(&mut violations);
+
+    match violations.errors.is_empty() {
+        true => Ok(()),
+        false => Err(ValidationError {
+            errors: violations.errors,
+        }),
+    }
+}
+
+/// Used by the `json_body` filters, answers 422 listing every broken rule
+pub async fn validated<T: Validate>(body: T) -> Result<T, Rejection> {
+    check(&body).map(|_| body).map_err(reject::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(check: impl FnOnce(&mut Violations)) -> Vec<&'static str> {
+        let mut violations = Violations::default();
+        check(&mut violations);
+        violations.errors.iter().map(|error| error.code).collect()
+    }
+
+    #[test]
+    fn accepts_valid_username() {
+        assert!(codes(|v| v.username("username", "nova_user1")).is_empty());
+        assert!(codes(|v| v.username("username", "  bob  ")).is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_usernames() {
+        assert_eq!(codes(|v| v.username("username", "   ")), [REQUIRED]);
+        assert_eq!(codes(|v| v.username("username", "ab")), [TOO_SHORT]);
+        assert_eq!(
+            codes(|v| v.username("username", &"a".repeat(USERNAME_MAX + 1))),
+            [TOO_LONG]
+        );
+        assert_eq!(
+            codes(|v| v.username("username", "bob smith")),
+            [INVALID_FORMAT]
+        );
+        assert_eq!(codes(|v| v.username("username", "bøb")), [INVALID_FORMAT]);
+    }
+
+    #[test]
+    fn accepts_valid_email() {
+        assert!(codes(|v| v.email("email", "bob@example.com")).is_empty());
+        assert!(codes(|v| v.email("email", " bob@mail.example.org ")).is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_emails() {
+        assert_eq!(codes(|v| v.email("email", "")), [REQUIRED]);
+        for email in [
+            "bob",
+            "@example.com",
+            "bob@example",
+            "bob@.example.com",
+            "bob@example.com.",
+            "bob@ex@ample.com",
+            "bo b@example.com",
+        ] {
+            assert_eq!(
+                codes(|v| v.email("email", email)),
+                [INVALID_FORMAT],
+                "{email}"
+            );
+        }
+        let long = format!("{}@example.com", "a".repeat(EMAIL_MAX));
+        assert_eq!(codes(|v| v.email("email", &long)), [TOO_LONG]);
+    }
+
+    #[test]
+    fn checks_password_length() {
+        assert!(codes(|v| v.password("password", "correct horse")).is_empty());
+        assert_eq!(codes(|v| v.password("password", "")), [REQUIRED]);
+        assert_eq!(codes(|v| v.password("password", "short")), [TOO_SHORT]);
+        assert_eq!(
+            codes(|v| v.password("password", &"a".repeat(PASSWORD_MAX + 1))),
+            [TOO_LONG]
+        );
+    }
+
+    #[test]
+    fn counts_password_characters_not_bytes() {
+        assert!(codes(|v| v.password("password", &"ä".repeat(PASSWORD_MAX))).is_empty());
+    }
+
+    #[test]
+    fn keeps_first_error_per_field() {
+        let errors = codes(|v| {
+            v.username("username", "");
+            v.add("username", TAKEN, "Is already taken");
+            v.email("email", "bob");
+        });
+
+        assert_eq!(errors, [REQUIRED, INVALID_FORMAT]);
+    }
+
+    #[test]
+    fn check_lists_every_broken_rule() {
+        struct Body;
+
+        impl Validate for Body {
+            fn validate(&self, violations: &mut Violations) {
+                violations.username("username", "a");
+                violations.password("password", "");
+            }
+        }
+
+        let error = check(&Body).unwrap_err();
+        let fields: Vec<&str> = error.errors.iter().map(|error| error.field).collect();
+
+        assert_eq!(fields, ["username", "password"]);
+    }
+}